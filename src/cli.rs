@@ -1,4 +1,7 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::utils::validate_reference;
 
 /// # wenzetu v0.1.0
 #[derive(Parser)]
@@ -14,25 +17,141 @@ pub struct Cli {
 pub enum Commands {
     /// Create a new application from a template
     #[command(name = "create-app")]
-    CreateApp {
-        /// Name of the application
-        name: String,
+    CreateApp(Box<CreateAppArgs>),
+
+    /// Browse the templates in a repository
+    Templates {
+        #[command(subcommand)]
+        action: TemplatesAction,
+    },
+
+    /// Manage the local template cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Run the project with cargo watch
+    Dev,
+}
+
+/// Options of `create-app`
+#[derive(Args)]
+pub struct CreateAppArgs {
+    /// Name of the application
+    pub name: String,
+
+    /// Template to use, or path to a local template directory (default: default)
+    #[arg(short, long, default_value = "default")]
+    pub template: String,
+
+    /// Layer to apply on top of the template, from the same repository or a local path (can be repeated, applied in order)
+    #[arg(long = "with", value_name = "LAYER")]
+    pub layers: Vec<String>,
+
+    /// Repository URL (GitHub, GitLab, Gitea, Bitbucket), git URL to clone (git@..., ssh://, git+https://), GitHub shorthand (e.g., username/repo), local directory or archive (file://...)
+    #[arg(short, long)]
+    pub repo: Option<String>,
+
+    /// Branch to use (default: main, or the ref listed in the registry)
+    #[arg(short, long, value_parser = reference)]
+    pub branch: Option<String>,
+
+    /// Tag or commit SHA to pin the template to (instead of a branch)
+    #[arg(long = "ref", value_name = "REF", conflicts_with = "branch", value_parser = reference)]
+    pub reference: Option<String>,
+
+    /// Only use templates from the local cache
+    #[arg(long, conflicts_with = "refresh")]
+    pub offline: bool,
+
+    /// Download the template again even if it is cached
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Git host of the repository: github, gitlab, gitea or bitbucket (detected from the URL by default)
+    #[arg(long)]
+    pub provider: Option<String>,
+
+    /// Base URL of the git host API (e.g., for self-hosted servers)
+    #[arg(long)]
+    pub api_url: Option<String>,
+
+    /// Expected sha256 checksum of the template archive
+    #[arg(long, value_name = "HEX", conflicts_with = "offline")]
+    pub sha256: Option<String>,
+
+    /// Minisign signature of the template archive (file or URL), checked against the public_key in the config file
+    #[arg(long, value_name = "FILE|URL", conflicts_with = "offline")]
+    pub signature: Option<String>,
 
-        /// Template to use (default: default)
-        #[arg(short, long, default_value = "default")]
-        template: String,
+    /// Registry index file or URL to look template names up in (can be repeated)
+    #[arg(long = "registry", value_name = "FILE|URL")]
+    pub registries: Vec<String>,
 
-        /// GitHub repository URL or shorthand (e.g., username/repo)
+    /// Value for a template variable (can be repeated)
+    #[arg(long = "var", value_name = "KEY=VALUE")]
+    pub vars: Vec<String>,
+
+    /// TOML file with values for template variables
+    #[arg(long, value_name = "FILE")]
+    pub vars_file: Option<PathBuf>,
+
+    /// Run the template's post-generation hooks without asking
+    #[arg(long, conflicts_with = "no_hooks")]
+    pub trust: bool,
+
+    /// Don't run the template's post-generation hooks
+    #[arg(long)]
+    pub no_hooks: bool,
+}
+
+#[derive(Subcommand)]
+pub enum TemplatesAction {
+    /// List the templates in a repository, with their descriptions
+    List {
+        /// Repository URL, git URL, GitHub shorthand, local directory or archive (default: erickweyunga/uncovr-templates)
         #[arg(short, long)]
         repo: Option<String>,
 
         /// Branch to use (default: main)
-        #[arg(short, long, default_value = "main")]
+        #[arg(short, long, default_value = "main", value_parser = reference)]
         branch: String,
+
+        /// Tag or commit SHA to list (instead of a branch)
+        #[arg(long = "ref", value_name = "REF", conflicts_with = "branch", value_parser = reference)]
+        reference: Option<String>,
+
+        /// Git host of the repository: github, gitlab, gitea or bitbucket (detected from the URL by default)
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Base URL of the git host API (e.g., for self-hosted servers)
+        #[arg(long)]
+        api_url: Option<String>,
     },
 
-    /// Run the project with cargo watch
-    Dev,
+    /// Search the registry indexes by name, description and tags
+    Search {
+        /// Words to look for
+        query: String,
+
+        /// Registry index file or URL to search (can be repeated)
+        #[arg(long = "registry", value_name = "FILE|URL")]
+        registries: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// List cached template repositories
+    List,
+
+    /// Remove cached snapshots no longer referenced by any branch
+    Prune,
+
+    /// Remove all cached templates
+    Clear,
 }
 
 impl Cli {
@@ -40,3 +159,9 @@ impl Cli {
         Self::parse()
     }
 }
+
+/// Parses a `--branch` or `--ref` value, see [`validate_reference`]
+fn reference(value: &str) -> anyhow::Result<String> {
+    validate_reference(value)?;
+    Ok(value.to_string())
+}
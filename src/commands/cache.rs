@@ -0,0 +1,70 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::cli::CacheAction;
+use crate::template::TemplateCache;
+
+/// Manages the local template cache
+///
+/// # Arguments
+///
+/// * `action` - The cache operation to perform
+///
+/// # Returns
+///
+/// Returns `Ok(())` if successful, or an error if the cache cannot be read or modified
+pub fn cache(action: CacheAction) -> Result<()> {
+    let cache = TemplateCache::open()?;
+
+    match action {
+        CacheAction::List => list_entries(&cache),
+        CacheAction::Prune => {
+            let removed = cache.prune()?;
+            println!("{} {} snapshot(s)", "removed".green(), removed);
+            Ok(())
+        }
+        CacheAction::Clear => {
+            cache.clear()?;
+            println!("{}", "template cache cleared".green());
+            Ok(())
+        }
+    }
+}
+
+/// Prints every cached repository reference
+fn list_entries(cache: &TemplateCache) -> Result<()> {
+    let entries = cache.entries()?;
+
+    if entries.is_empty() {
+        println!("{}", "template cache is empty".dimmed());
+        return Ok(());
+    }
+
+    println!("{}", cache.root().display().to_string().dimmed());
+    for entry in entries {
+        println!(
+            "  {} {} {}",
+            entry.repo.cyan(),
+            entry.reference,
+            format_commit(&entry.commit).dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Formats a commit id for display
+fn format_commit(commit: &str) -> String {
+    commit.chars().take(7).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_commit() {
+        assert_eq!(format_commit("0123456789abcdef"), "0123456");
+        assert_eq!(format_commit("abc"), "abc");
+    }
+}
@@ -1,32 +1,62 @@
 use anyhow::Result;
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 
-use crate::template::{download_template, normalize_repo_url, replace_placeholders};
+use crate::template::hooks::run_hooks;
+use crate::template::layers::layer_source;
+use crate::template::source::DEFAULT_REF;
+use crate::template::variables::{prompt_value, provided_values};
+use crate::template::version::{TemplateSpec, resolve_version};
+use crate::template::{
+    DownloadOptions, HookPolicy, LayerStack, Registry, Renderer, TemplateSource,
+};
 use crate::utils::{
     ensure_cargo_watch, ensure_directory, get_run_command, init_git_repo, validate_project_name,
 };
 
+/// Options for creating an application
+#[derive(Debug, Clone)]
+pub struct CreateAppOptions {
+    /// Template name, or path to a local template directory
+    pub template: String,
+    /// Layers applied on top of the template, in order
+    pub layers: Vec<String>,
+    /// Optional repository URL, GitHub shorthand, local directory or archive
+    pub repo: Option<String>,
+    /// Branch, tag or commit SHA to use from the repository (default: main,
+    /// or the ref listed in the registry)
+    pub reference: Option<String>,
+    /// Registry index files or URLs to look the template name up in
+    pub registries: Vec<String>,
+    /// Template variable values as `key=value`
+    pub vars: Vec<String>,
+    /// TOML file with template variable values
+    pub vars_file: Option<PathBuf>,
+    /// Whether to run the template's post-generation hooks
+    pub hooks: HookPolicy,
+    /// Cache and network options for the template download
+    pub download: DownloadOptions,
+}
+
 /// Creates a new application from a template
 ///
 /// # Arguments
 ///
 /// * `name` - Name of the application
-/// * `template` - Template to use
-/// * `repo` - Optional GitHub repository URL or shorthand
-/// * `branch` - Branch to use from the repository
+/// * `options` - Template source and download options
 ///
 /// # Returns
 ///
 /// Returns `Ok(())` if successful, or an error if creation fails
-pub fn create_app(name: &str, template: &str, repo: Option<&str>, branch: &str) -> Result<()> {
+pub fn create_app(name: &str, options: &CreateAppOptions) -> Result<()> {
     // Validate project name
     validate_project_name(name)?;
 
-    // Determine repository URL
-    let repo_url = repo.unwrap_or("erickweyunga/uncovr-templates");
-    let full_repo_url = normalize_repo_url(repo_url);
+    // Determine where the template comes from
+    let resolved = resolve_template(options)?;
+    let provided = provided_values(&options.vars, options.vars_file.as_deref())?;
 
     // Print creation info
     print_creation_info();
@@ -42,8 +72,50 @@ pub fn create_app(name: &str, template: &str, repo: Option<&str>, branch: &str)
 
     // Execute operations and cleanup on error
     let result = (|| -> Result<()> {
-        download_template(&full_repo_url, branch, template, &project_path)?;
-        replace_placeholders(&project_path, name)?;
+        // Downloads report their progress on the spinner
+        let download = DownloadOptions {
+            progress: Some(pb.clone()),
+            ..resolved.download.clone()
+        };
+        // Templates the template extends come before it, then the layers
+        let mut stack = LayerStack::default();
+        let mut provenance = stack.push(
+            &resolved.source,
+            &resolved.reference,
+            &resolved.template,
+            &download,
+        )?;
+        for layer in &options.layers {
+            let source = layer_source(&resolved.source, layer)?;
+            stack.push(&source, &resolved.reference, layer, &download)?;
+        }
+
+        // Collect values for the variables of every template
+        let variables = stack.variables();
+        let interactive = io::stdin().is_terminal();
+        let values = pb.suspend(|| {
+            variables.resolve_values(name, &provided, |variable| {
+                if interactive {
+                    prompt_value(variable).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
+        })?;
+
+        // Each layer is rendered on top of the ones before it
+        let renderer = Renderer::new(&values);
+        let mut hooks = Vec::new();
+        for layer in stack.layers() {
+            let filter = layer.manifest.path_filter(&renderer)?;
+            renderer.render_dir(&layer.fetched.dir, &project_path, &filter)?;
+            hooks.extend(layer.manifest.hook_commands(&renderer)?);
+        }
+        provenance.layers = stack.records();
+        provenance.write(&project_path)?;
+
+        // Run the hooks once all files are in place
+        pb.suspend(|| run_hooks(&hooks, &project_path, options.hooks, interactive))?;
         init_git_repo(&project_path)?;
         Ok(())
     })();
@@ -68,6 +140,86 @@ pub fn create_app(name: &str, template: &str, repo: Option<&str>, branch: &str)
     Ok(())
 }
 
+/// A template source, after looking its name up in the registries
+struct ResolvedTemplate {
+    source: TemplateSource,
+    template: String,
+    reference: String,
+    download: DownloadOptions,
+}
+
+/// Determines where the template comes from
+///
+/// Without `--repo`, a template name listed in a registry index resolves to
+/// the repository, ref and checksum from the index. Values given on the
+/// command line win over the index. Names no index lists come from the
+/// default repository, which is also where names end up offline or when an
+/// index URL can't be downloaded.
+///
+/// A version requirement (`default@^1.2`) picks the newest matching version
+/// listed in the registry, or else the newest matching tag of the
+/// repository.
+fn resolve_template(options: &CreateAppOptions) -> Result<ResolvedTemplate> {
+    let reference = options
+        .reference
+        .clone()
+        .unwrap_or_else(|| DEFAULT_REF.to_string());
+
+    let source = TemplateSource::parse(options.repo.as_deref(), &options.template)?;
+    let mut download = options.download.clone();
+    if options.repo.is_some() {
+        download.repo_host = source.host();
+    }
+    if let TemplateSource::LocalTemplate(_) = source {
+        return Ok(ResolvedTemplate {
+            source,
+            template: options.template.clone(),
+            reference,
+            download,
+        });
+    }
+
+    let spec = TemplateSpec::parse(&options.template)?;
+    if spec.requirement.is_some() && options.reference.is_some() {
+        anyhow::bail!("A template version can't be combined with --branch or --ref");
+    }
+
+    let mut resolved = ResolvedTemplate {
+        source,
+        template: spec.name.clone(),
+        reference,
+        download,
+    };
+
+    if options.repo.is_none() {
+        let registry = Registry::load(&options.registries, options.download.offline)?;
+        if let Some(entry) = registry.find(&spec.name, spec.requirement.as_ref())? {
+            resolved.source = entry.template_source()?;
+            resolved.template = entry.template_name().to_string();
+            if let Some(reference) = options.reference.clone().or_else(|| entry.reference()) {
+                resolved.reference = reference;
+            }
+            if resolved.download.verify.sha256.is_none() {
+                resolved.download.verify.sha256 = entry.sha256.clone();
+            }
+            if entry.version.is_some() {
+                return Ok(resolved);
+            }
+        }
+    }
+
+    if let Some(requirement) = &spec.requirement {
+        resolved.reference = resolve_version(
+            &resolved.source,
+            &resolved.template,
+            requirement,
+            &resolved.download,
+        )?;
+    }
+
+    Ok(resolved)
+}
+
 /// Prints information about the project being created
 fn print_creation_info() {
     println!("{}", "setting up your project...".green().bold());
@@ -75,7 +227,7 @@ fn print_creation_info() {
 }
 
 /// Checks if a directory already exists and returns an error if it does
-fn check_directory_exists(path: &PathBuf, name: &str) -> Result<()> {
+fn check_directory_exists(path: &Path, name: &str) -> Result<()> {
     if path.exists() {
         anyhow::bail!("Directory '{}' already exists", name);
     }
@@ -107,7 +259,7 @@ fn print_success_message(name: &str) {
 }
 
 /// Cleans up a directory (removes it) when an error occurs
-fn cleanup_directory(path: &PathBuf) {
+fn cleanup_directory(path: &Path) {
     use std::fs;
     if path.exists() {
         let _ = fs::remove_dir_all(path);
@@ -117,6 +269,8 @@ fn cleanup_directory(path: &PathBuf) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::template::download::normalize_repo_url;
+    use crate::template::source::DEFAULT_REPO;
     use std::fs;
     use tempfile::TempDir;
 
@@ -132,6 +286,92 @@ mod tests {
         assert_eq!(url, "https://github.com/user/repo");
     }
 
+    #[test]
+    fn test_resolve_template_from_registry() {
+        let temp_dir = TempDir::new().unwrap();
+        let index = temp_dir.path().join("index.toml");
+        fs::write(
+            &index,
+            "[[templates]]\nname = \"api\"\nsource = \"acme/templates\"\ntemplate = \"axum-api\"\nref = \"v1.0.0\"\nsha256 = \"abc\"\n",
+        )
+        .unwrap();
+        let options = |template: &str, reference: Option<&str>| CreateAppOptions {
+            template: template.to_string(),
+            layers: Vec::new(),
+            repo: None,
+            reference: reference.map(str::to_string),
+            registries: vec![index.to_string_lossy().to_string()],
+            vars: Vec::new(),
+            vars_file: None,
+            hooks: HookPolicy::Skip,
+            download: DownloadOptions::default(),
+        };
+
+        let resolved = resolve_template(&options("api", None)).unwrap();
+        assert_eq!(
+            resolved.source,
+            TemplateSource::Remote("https://github.com/acme/templates".to_string())
+        );
+        assert_eq!(resolved.template, "axum-api");
+        assert_eq!(resolved.reference, "v1.0.0");
+        assert_eq!(resolved.download.verify.sha256.as_deref(), Some("abc"));
+
+        // Listed names win over the default repository
+        fs::write(
+            &index,
+            "[[templates]]\nname = \"default\"\nsource = \"acme/templates\"\nsha256 = \"abc\"\n",
+        )
+        .unwrap();
+        let resolved = resolve_template(&options("default", None)).unwrap();
+        assert_eq!(
+            resolved.source,
+            TemplateSource::Remote("https://github.com/acme/templates".to_string())
+        );
+        assert_eq!(resolved.download.verify.sha256.as_deref(), Some("abc"));
+        fs::write(
+            &index,
+            "[[templates]]\nname = \"api\"\nsource = \"acme/templates\"\ntemplate = \"axum-api\"\nref = \"v1.0.0\"\nsha256 = \"abc\"\n",
+        )
+        .unwrap();
+
+        // --ref wins over the index
+        let resolved = resolve_template(&options("api", Some("main"))).unwrap();
+        assert_eq!(resolved.reference, "main");
+
+        // Unlisted names come from the default repository
+        let resolved = resolve_template(&options("default", None)).unwrap();
+        assert_eq!(resolved.template, "default");
+        assert_eq!(resolved.reference, "main");
+        assert_eq!(resolved.download.verify.sha256, None);
+
+        // Versions listed in the index are picked without asking the host
+        fs::write(
+            &index,
+            "[[templates]]\nname = \"api\"\nsource = \"acme/templates\"\nversion = \"1.2.0\"\n\n[[templates]]\nname = \"api\"\nsource = \"acme/templates\"\nversion = \"1.3.1\"\n\n[[templates]]\nname = \"api\"\nsource = \"acme/templates\"\nversion = \"2.0.0\"\n",
+        )
+        .unwrap();
+        let resolved = resolve_template(&options("api@^1.2", None)).unwrap();
+        assert_eq!(resolved.template, "api");
+        assert_eq!(resolved.reference, "v1.3.1");
+        assert!(resolve_template(&options("api@^1.2", Some("main"))).is_err());
+
+        // Remote indexes are skipped offline, falling back to the default repository
+        let offline = CreateAppOptions {
+            registries: vec!["http://127.0.0.1:1/index.toml".to_string()],
+            download: DownloadOptions {
+                offline: true,
+                ..Default::default()
+            },
+            ..options("default", None)
+        };
+        let resolved = resolve_template(&offline).unwrap();
+        assert_eq!(
+            resolved.source,
+            TemplateSource::Remote(format!("https://github.com/{}", DEFAULT_REPO))
+        );
+        assert_eq!(resolved.template, "default");
+    }
+
     #[test]
     fn test_cleanup_directory() {
         let temp_dir = TempDir::new().unwrap();
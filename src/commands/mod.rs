@@ -1,11 +1,16 @@
+pub mod cache;
 pub mod create_app;
 pub mod dev;
+pub mod templates;
 
 use anyhow::Result;
 
-use crate::cli::Commands;
-pub use create_app::create_app;
+use crate::cli::{Commands, CreateAppArgs};
+use crate::template::{DownloadOptions, HookPolicy, Verification};
+pub use cache::cache;
+pub use create_app::{CreateAppOptions, create_app};
 pub use dev::dev;
+pub use templates::templates;
 
 /// Dispatches commands to their respective handlers
 ///
@@ -18,12 +23,56 @@ pub use dev::dev;
 /// Returns `Ok(())` if the command executes successfully, or an error otherwise
 pub fn dispatch(command: Commands) -> Result<()> {
     match command {
-        Commands::CreateApp {
-            name,
-            template,
-            repo,
-            branch,
-        } => create_app(&name, &template, repo.as_deref(), &branch),
+        Commands::CreateApp(args) => {
+            let CreateAppArgs {
+                name,
+                template,
+                layers,
+                repo,
+                branch,
+                reference,
+                offline,
+                refresh,
+                provider,
+                api_url,
+                sha256,
+                signature,
+                registries,
+                vars,
+                vars_file,
+                trust,
+                no_hooks,
+            } = *args;
+            create_app(
+                &name,
+                &CreateAppOptions {
+                    template,
+                    layers,
+                    repo,
+                    reference: reference.or(branch),
+                    registries,
+                    vars,
+                    vars_file,
+                    hooks: if no_hooks {
+                        HookPolicy::Skip
+                    } else if trust {
+                        HookPolicy::Trust
+                    } else {
+                        HookPolicy::Ask
+                    },
+                    download: DownloadOptions {
+                        offline,
+                        refresh,
+                        provider,
+                        api_url,
+                        verify: Verification { sha256, signature },
+                        ..Default::default()
+                    },
+                },
+            )
+        }
+        Commands::Templates { action } => templates(action),
+        Commands::Cache { action } => cache(action),
         Commands::Dev => dev(),
     }
 }
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::cli::TemplatesAction;
+use crate::template::catalog::{TemplateInfo, list_templates};
+use crate::template::registry::RegistryEntry;
+use crate::template::source::DEFAULT_REPO;
+use crate::template::{DownloadOptions, Registry, TemplateSource};
+
+/// Browses the templates in a repository
+///
+/// # Arguments
+///
+/// * `action` - The templates operation to perform
+///
+/// # Returns
+///
+/// Returns `Ok(())` if successful, or an error if the repository can't be read
+pub fn templates(action: TemplatesAction) -> Result<()> {
+    match action {
+        TemplatesAction::List {
+            repo,
+            branch,
+            reference,
+            provider,
+            api_url,
+        } => {
+            let source = TemplateSource::parse(repo.as_deref(), "default")?;
+            let options = DownloadOptions {
+                provider,
+                api_url,
+                repo_host: repo.as_ref().and_then(|_| source.host()),
+                ..Default::default()
+            };
+            let templates = list_templates(&source, &reference.unwrap_or(branch), &options)?;
+
+            println!("{}", repo.as_deref().unwrap_or(DEFAULT_REPO).dimmed());
+            print_templates(&templates);
+            Ok(())
+        }
+        TemplatesAction::Search { query, registries } => {
+            let registry = Registry::load(&registries, false)?;
+            if registry.is_empty() {
+                anyhow::bail!(
+                    "No registry indexes configured. Pass --registry or add registries to the config file."
+                );
+            }
+
+            print_matches(&registry.search(&query));
+            Ok(())
+        }
+    }
+}
+
+/// Prints registry search results with their tags and source
+fn print_matches(entries: &[&RegistryEntry]) {
+    if entries.is_empty() {
+        println!("{}", "no matching templates".dimmed());
+        return;
+    }
+
+    for entry in entries {
+        let mut line = format!("  {}", entry.name.cyan());
+        if let Some(description) = &entry.description {
+            line.push_str(&format!("  {}", description));
+        }
+        if !entry.tags.is_empty() {
+            line.push_str(&format!(
+                "  {}",
+                format!("[{}]", entry.tags.join(", ")).dimmed()
+            ));
+        }
+        println!("{}", line);
+        println!("    {}", entry.source.dimmed());
+    }
+}
+
+/// Prints template names with their descriptions, aligned
+fn print_templates(templates: &[TemplateInfo]) {
+    if templates.is_empty() {
+        println!("{}", "no templates found".dimmed());
+        return;
+    }
+
+    let width = templates.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for template in templates {
+        match &template.description {
+            Some(description) => println!(
+                "  {}  {}",
+                format!("{:width$}", template.name, width = width).cyan(),
+                description
+            ),
+            None => println!("  {}", template.name.cyan()),
+        }
+    }
+}
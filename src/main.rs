@@ -1,6 +1,8 @@
 mod cli;
 mod commands;
 mod template;
+#[cfg(test)]
+mod test_support;
 mod utils;
 
 use anyhow::Result;
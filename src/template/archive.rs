@@ -0,0 +1,585 @@
+use anyhow::{Context, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use tar::EntryType;
+
+use super::catalog::not_found_error;
+use super::manifest::MANIFEST_FILE;
+use crate::utils::file_ops::symlink_stays_inside;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Hard limits applied when extracting template archives
+///
+/// Archives come from third-party repositories, so a hostile or broken
+/// archive must not be able to fill the disk or write outside the
+/// destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractLimits {
+    /// Largest archive accepted, as downloaded
+    pub max_compressed: u64,
+    /// Largest total size of the extracted files
+    pub max_uncompressed: u64,
+    /// Most entries (files, directories and links) in the archive
+    pub max_entries: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_compressed: 100 * MIB,
+            max_uncompressed: 500 * MIB,
+            max_entries: 20_000,
+        }
+    }
+}
+
+impl ExtractLimits {
+    /// Error for an archive over the compressed size limit
+    pub fn compressed_error(&self) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Template archive is larger than the {} limit",
+            format_size(self.max_compressed)
+        )
+    }
+}
+
+/// Extracts a tarball (gzipped or plain) to a destination directory
+///
+/// # Arguments
+///
+/// * `bytes` - Tarball bytes
+/// * `dest` - Destination directory path
+pub fn extract_tarball(bytes: &[u8], dest: &Path) -> Result<()> {
+    extract_tarball_with(bytes, dest, &ExtractLimits::default())
+}
+
+/// Extracts a tarball with explicit limits
+///
+/// # Arguments
+///
+/// * `bytes` - Tarball bytes
+/// * `dest` - Destination directory path
+/// * `limits` - Size and entry count limits
+pub fn extract_tarball_with(bytes: &[u8], dest: &Path, limits: &ExtractLimits) -> Result<()> {
+    if bytes.len() as u64 > limits.max_compressed {
+        return Err(limits.compressed_error());
+    }
+
+    extract_stream(bytes, dest, limits, |path| Some(path.to_path_buf()))?;
+    Ok(())
+}
+
+/// Extracts one template from a repository archive as it is read
+///
+/// Host archives wrap the repository in a single top-level directory. Only
+/// entries under `<root>/<template>/` are extracted, into `dest/<template>`.
+///
+/// # Arguments
+///
+/// * `reader` - Archive stream (gzipped or plain tar)
+/// * `template` - Template name (directory name in the repo)
+/// * `dest` - Destination directory path
+/// * `limits` - Size and entry count limits
+///
+/// # Returns
+///
+/// Returns `Ok(())` if successful, or an error if the template is not in
+/// the archive (suggesting similar names) or the archive breaks a limit
+pub fn extract_template<R: Read>(
+    reader: R,
+    template: &str,
+    dest: &Path,
+    limits: &ExtractLimits,
+) -> Result<()> {
+    let template_path = Path::new(template);
+    let names = RefCell::new(BTreeSet::new());
+    let extracted = extract_stream(reader, dest, limits, |path| {
+        let in_repo = strip_root(path)?;
+        if let Some(name) = template_name(in_repo) {
+            names.borrow_mut().insert(name);
+        }
+        in_repo
+            .starts_with(template_path)
+            .then(|| in_repo.to_path_buf())
+    })?;
+
+    if extracted == 0 {
+        let available: Vec<String> = names.into_inner().into_iter().collect();
+        return Err(not_found_error(template, &available));
+    }
+    Ok(())
+}
+
+/// Extracts only the manifest of every template in a repository archive
+///
+/// # Arguments
+///
+/// * `reader` - Archive stream (gzipped or plain tar)
+/// * `dest` - Destination directory, receives `<template>/template.toml`
+/// * `limits` - Size and entry count limits
+///
+/// # Returns
+///
+/// Returns the sorted names of the templates in the archive
+pub fn extract_manifests<R: Read>(
+    reader: R,
+    dest: &Path,
+    limits: &ExtractLimits,
+) -> Result<Vec<String>> {
+    let names = RefCell::new(BTreeSet::new());
+    extract_stream(reader, dest, limits, |path| {
+        let in_repo = strip_root(path)?;
+        let name = template_name(in_repo)?;
+        let manifest = Path::new(&name).join(MANIFEST_FILE);
+        names.borrow_mut().insert(name);
+        (in_repo == manifest).then_some(manifest)
+    })?;
+
+    Ok(names.into_inner().into_iter().collect())
+}
+
+/// Strips the top-level directory host archives wrap repositories in
+fn strip_root(path: &Path) -> Option<&Path> {
+    let mut components = path.components();
+    components.next()?;
+    Some(components.as_path())
+}
+
+/// Returns the template an entry belongs to, if it is inside a
+/// non-hidden top-level directory
+fn template_name(in_repo: &Path) -> Option<String> {
+    let mut components = in_repo.components();
+    let name = components.next()?.as_os_str().to_string_lossy();
+    // Top-level files have nothing after their name
+    components.next()?;
+    (!name.starts_with('.')).then(|| name.to_string())
+}
+
+/// Extracts the entries `select` maps to a path inside `dest`
+///
+/// Entries with absolute paths or `..` components are rejected, as are
+/// entries under a symlink extracted earlier. Symlinks are kept only if
+/// they are relative and stay inside the destination; hardlinks are
+/// rejected. Files keep the permission bits of the archive. Devices, FIFOs
+/// and other special entries are skipped.
+///
+/// # Returns
+///
+/// Returns the number of entries extracted, or an error naming the limit
+/// or entry that made extraction stop
+fn extract_stream<R, F>(reader: R, dest: &Path, limits: &ExtractLimits, select: F) -> Result<u64>
+where
+    R: Read,
+    F: Fn(&Path) -> Option<PathBuf>,
+{
+    let received = Rc::new(Cell::new(0));
+    let mut reader = BufReader::new(LimitedReader {
+        inner: reader,
+        received: received.clone(),
+        limit: limits.max_compressed,
+    });
+
+    let result = reader
+        .fill_buf()
+        .context("Failed to read template archive")
+        .map(|head| head.starts_with(&[0x1f, 0x8b]))
+        .and_then(|is_gzip| {
+            if is_gzip {
+                unpack(flate2::read::GzDecoder::new(reader), dest, limits, select)
+            } else {
+                unpack(reader, dest, limits, select)
+            }
+        });
+
+    // Reads past the limit fail inside tar, report them as the limit
+    if received.get() > limits.max_compressed {
+        return Err(limits.compressed_error());
+    }
+    result
+}
+
+/// Unpacks a tar stream entry by entry, checking each one first
+fn unpack<R, F>(reader: R, dest: &Path, limits: &ExtractLimits, select: F) -> Result<u64>
+where
+    R: Read,
+    F: Fn(&Path) -> Option<PathBuf>,
+{
+    fs::create_dir_all(dest).context("Failed to extract tarball")?;
+    let mut archive = tar::Archive::new(reader);
+    let mut entries_seen = 0;
+    let mut extracted = 0;
+    let mut total_size = 0;
+    let mut symlinks: Vec<PathBuf> = Vec::new();
+
+    for entry in archive.entries().context("Failed to extract tarball")? {
+        let mut entry = entry.context("Failed to extract tarball")?;
+
+        entries_seen += 1;
+        if entries_seen > limits.max_entries {
+            anyhow::bail!(
+                "Template archive has more than {} entries",
+                limits.max_entries
+            );
+        }
+
+        let path = entry
+            .path()
+            .context("Invalid path in tarball")?
+            .into_owned();
+        check_entry_path(&path)?;
+        let Some(target) = select(&path) else {
+            continue;
+        };
+        check_through_symlink(&path, &target, &symlinks)?;
+
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous | EntryType::Directory => {}
+            EntryType::Symlink => {
+                let link = entry
+                    .link_name()
+                    .context("Invalid symlink in tarball")?
+                    .ok_or_else(|| anyhow::anyhow!("Invalid symlink in tarball"))?;
+                check_symlink(&target, &link)?;
+                symlinks.push(target.clone());
+            }
+            EntryType::Link => anyhow::bail!(
+                "Template archive contains a hardlink ('{}'), which is not allowed",
+                path.display()
+            ),
+            _ => continue,
+        }
+
+        total_size += entry.size();
+        if total_size > limits.max_uncompressed {
+            anyhow::bail!(
+                "Template archive expands to more than the {} limit",
+                format_size(limits.max_uncompressed)
+            );
+        }
+
+        let target_path = dest.join(&target);
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent).context("Failed to extract tarball")?;
+        }
+        entry
+            .unpack(&target_path)
+            .map_err(|e| io_error(e, &path))
+            .context("Failed to extract tarball")?;
+        extracted += 1;
+    }
+
+    Ok(extracted)
+}
+
+/// Counts bytes read and fails once more than `limit` have been read
+struct LimitedReader<R> {
+    inner: R,
+    received: Rc<Cell<u64>>,
+    limit: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.received.set(self.received.get() + n as u64);
+        if self.received.get() > self.limit {
+            return Err(io::Error::other("template archive is too large"));
+        }
+        Ok(n)
+    }
+}
+
+/// Reports the number of bytes read so far to a callback
+pub struct ProgressReader<R, F> {
+    inner: R,
+    received: u64,
+    on_progress: F,
+}
+
+impl<R, F: FnMut(u64)> ProgressReader<R, F> {
+    pub fn new(inner: R, on_progress: F) -> Self {
+        Self {
+            inner,
+            received: 0,
+            on_progress,
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.received += n as u64;
+        (self.on_progress)(self.received);
+        Ok(n)
+    }
+}
+
+/// Rejects absolute paths and paths that climb out of the destination
+fn check_entry_path(path: &Path) -> Result<()> {
+    let escapes = path.components().any(|component| {
+        matches!(
+            component,
+            Component::RootDir | Component::Prefix(_) | Component::ParentDir
+        )
+    });
+
+    if escapes {
+        anyhow::bail!(
+            "Template archive entry '{}' points outside the destination",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Rejects symlinks that are absolute or resolve outside the destination
+fn check_symlink(path: &Path, target: &Path) -> Result<()> {
+    if !symlink_stays_inside(path, target) {
+        anyhow::bail!(
+            "Template archive symlink '{}' -> '{}' points outside the destination",
+            path.display(),
+            target.display()
+        );
+    }
+    Ok(())
+}
+
+/// Rejects entries whose parent directory is a symlink extracted earlier,
+/// which could lead anywhere once followed
+fn check_through_symlink(path: &Path, target: &Path, symlinks: &[PathBuf]) -> Result<()> {
+    if let Some(link) = symlinks
+        .iter()
+        .find(|link| target.starts_with(link) && target != link.as_path())
+    {
+        anyhow::bail!(
+            "Template archive entry '{}' goes through the symlink '{}', which is not allowed",
+            path.display(),
+            link.display()
+        );
+    }
+    Ok(())
+}
+
+fn io_error(error: io::Error, path: &Path) -> anyhow::Error {
+    anyhow::anyhow!("{} ({})", error, path.display())
+}
+
+/// Formats a byte count, e.g. `100 MiB`
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= MIB {
+        format!("{} MiB", bytes / MIB)
+    } else if bytes >= 1024 {
+        format!("{} KiB", bytes / 1024)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::tarball;
+    use tempfile::TempDir;
+
+    /// Builds a plain tarball with raw headers, bypassing the path checks
+    /// of `tar::Builder`
+    fn raw_tarball(entries: &[(&str, EntryType, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, kind, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*kind);
+            header.set_mode(0o644);
+            {
+                let name = &mut header.as_old_mut().name;
+                name[..path.len()].copy_from_slice(path.as_bytes());
+            }
+            if matches!(kind, EntryType::Symlink | EntryType::Link) {
+                header.set_link_name(contents).unwrap();
+                header.set_size(0);
+                header.set_cksum();
+                builder.append(&header, io::empty()).unwrap();
+            } else {
+                header.set_size(contents.len() as u64);
+                header.set_cksum();
+                builder.append(&header, contents.as_bytes()).unwrap();
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn extract(bytes: &[u8]) -> Result<TempDir> {
+        let temp_dir = TempDir::new().unwrap();
+        extract_tarball(bytes, &temp_dir.path().join("dest"))?;
+        Ok(temp_dir)
+    }
+
+    #[test]
+    fn test_extract_tarball() {
+        let temp_dir = extract(&tarball(&[("repo/default/Cargo.toml", "name")])).unwrap();
+        assert!(
+            temp_dir
+                .path()
+                .join("dest/repo/default/Cargo.toml")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_rejects_escaping_paths() {
+        let err = extract(&raw_tarball(&[("../evil.txt", EntryType::Regular, "x")]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("points outside the destination"));
+
+        assert!(extract(&raw_tarball(&[("/tmp/evil.txt", EntryType::Regular, "x")])).is_err());
+    }
+
+    #[test]
+    fn test_link_policy() {
+        let temp_dir = extract(&raw_tarball(&[
+            ("repo/a.txt", EntryType::Regular, "a"),
+            ("repo/b.txt", EntryType::Symlink, "a.txt"),
+        ]))
+        .unwrap();
+        assert!(temp_dir.path().join("dest/repo/b.txt").is_symlink());
+
+        assert!(
+            extract(&raw_tarball(&[(
+                "repo/x",
+                EntryType::Symlink,
+                "/etc/passwd"
+            )]))
+            .is_err()
+        );
+        assert!(extract(&raw_tarball(&[("repo/x", EntryType::Symlink, "../../x")])).is_err());
+        // A link through another link can't climb out either
+        assert!(
+            extract(&raw_tarball(&[
+                ("repo/d", EntryType::Symlink, ".."),
+                ("repo/e", EntryType::Symlink, "d/../x"),
+            ]))
+            .is_err()
+        );
+
+        // Nor can an entry be written through a directory link
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("a/b/dest");
+        let err = extract_tarball(
+            &raw_tarball(&[
+                ("repo/d", EntryType::Symlink, ".."),
+                ("repo/d/e", EntryType::Symlink, ".."),
+                ("repo/d/e/pwned.txt", EntryType::Regular, "x"),
+            ]),
+            &dest,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("goes through the symlink 'repo/d'"));
+        assert!(!temp_dir.path().join("a/b/pwned.txt").exists());
+        assert!(!temp_dir.path().join("a/pwned.txt").exists());
+
+        let err = extract(&raw_tarball(&[
+            ("repo/a.txt", EntryType::Regular, "a"),
+            ("repo/b.txt", EntryType::Link, "repo/a.txt"),
+        ]))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("hardlink"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let bytes = tarball(&[("a.txt", "aaaa"), ("b.txt", "bbbb"), ("c.txt", "cccc")]);
+        let limits = ExtractLimits::default();
+
+        let too_small = ExtractLimits {
+            max_compressed: 10,
+            ..limits
+        };
+        let err = extract_tarball_with(&bytes, temp_dir.path(), &too_small).unwrap_err();
+        assert!(err.to_string().contains("larger than the 10 B limit"));
+
+        let few_entries = ExtractLimits {
+            max_entries: 2,
+            ..limits
+        };
+        let err = extract_tarball_with(&bytes, temp_dir.path(), &few_entries).unwrap_err();
+        assert!(err.to_string().contains("more than 2 entries"));
+
+        let small_files = ExtractLimits {
+            max_uncompressed: 10,
+            ..limits
+        };
+        let err = extract_tarball_with(&bytes, temp_dir.path(), &small_files).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expands to more than the 10 B limit")
+        );
+    }
+
+    #[test]
+    fn test_extract_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let bytes = tarball(&[
+            ("repo-abc/default/Cargo.toml", "name"),
+            ("repo-abc/default/src/main.rs", "fn main() {}"),
+            ("repo-abc/default-tailwind/Cargo.toml", "tailwind"),
+            ("repo-abc/other/Cargo.toml", "other"),
+        ]);
+        let limits = ExtractLimits::default();
+
+        let mut progress = 0;
+        let reader = ProgressReader::new(bytes.as_slice(), |received| progress = received);
+        extract_template(reader, "default", temp_dir.path(), &limits).unwrap();
+        assert_eq!(progress, bytes.len() as u64);
+
+        assert!(temp_dir.path().join("default/src/main.rs").exists());
+        assert!(!temp_dir.path().join("default-tailwind").exists());
+        assert!(!temp_dir.path().join("other").exists());
+
+        let err = extract_template(bytes.as_slice(), "othr", temp_dir.path(), &limits)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Template 'othr' not found"));
+        assert!(err.contains("Did you mean 'other'?"));
+
+        // The size limit applies while streaming
+        let too_small = ExtractLimits {
+            max_compressed: 10,
+            ..limits
+        };
+        let err = extract_template(bytes.as_slice(), "default", temp_dir.path(), &too_small)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("larger than the 10 B limit"));
+    }
+
+    #[test]
+    fn test_extract_manifests() {
+        let temp_dir = TempDir::new().unwrap();
+        let bytes = tarball(&[
+            ("repo-abc/README.md", "templates"),
+            ("repo-abc/.github/workflows/ci.yml", "on: push"),
+            (
+                "repo-abc/default/template.toml",
+                "description = \"Default\"",
+            ),
+            ("repo-abc/default/src/main.rs", "fn main() {}"),
+            ("repo-abc/api/Cargo.toml", "api"),
+        ]);
+
+        let names = extract_manifests(bytes.as_slice(), temp_dir.path(), &ExtractLimits::default())
+            .unwrap();
+        assert_eq!(names, vec!["api", "default"]);
+        assert!(temp_dir.path().join("default/template.toml").exists());
+        assert!(!temp_dir.path().join("default/src").exists());
+        assert!(!temp_dir.path().join("api").exists());
+    }
+}
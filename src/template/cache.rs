@@ -0,0 +1,425 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Environment variable that overrides the cache location
+const CACHE_DIR_ENV: &str = "WENZETU_CACHE_DIR";
+
+/// A cached template snapshot as reported by [`TemplateCache::entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Repository key, e.g. `github.com/owner/repo`
+    pub repo: String,
+    /// Branch (or other reference) name
+    pub reference: String,
+    /// Commit the reference pointed to when it was cached
+    pub commit: String,
+}
+
+/// Persistent on-disk cache of extracted templates
+///
+/// Layout:
+///
+/// ```text
+/// <root>/<host>/<owner>/<repo>/snapshots/<commit>/...   extracted archive
+/// <root>/<host>/<owner>/<repo>/refs/<reference>         commit id
+/// ```
+pub struct TemplateCache {
+    root: PathBuf,
+}
+
+impl TemplateCache {
+    /// Opens the cache in its default location
+    ///
+    /// Uses `$WENZETU_CACHE_DIR` when set, otherwise the platform cache
+    /// directory (e.g. `~/.cache/wenzetu/templates` on Linux).
+    pub fn open() -> Result<Self> {
+        if let Some(dir) = std::env::var_os(CACHE_DIR_ENV) {
+            return Ok(Self::at(PathBuf::from(dir)));
+        }
+
+        let base = dirs::cache_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the cache directory"))?;
+        Ok(Self::at(base.join("wenzetu").join("templates")))
+    }
+
+    /// Opens a cache rooted at the given directory
+    pub fn at(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Returns the root directory of the cache
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the snapshot directory for a commit, if it has been cached
+    pub fn snapshot(&self, repo: &str, commit: &str) -> Option<PathBuf> {
+        let path = self.snapshot_path(repo, commit);
+        path.is_dir().then_some(path)
+    }
+
+    /// Looks up the last cached commit for a reference
+    ///
+    /// # Returns
+    ///
+    /// Returns the commit id and its snapshot directory, or `None` when the
+    /// reference has never been cached
+    pub fn lookup(&self, repo: &str, reference: &str) -> Option<(String, PathBuf)> {
+        let commit = fs::read_to_string(self.ref_path(repo, reference)).ok()?;
+        let commit = commit.trim().to_string();
+        let snapshot = self.snapshot(repo, &commit)?;
+        Some((commit, snapshot))
+    }
+
+    /// Points a reference at a commit
+    pub fn set_ref(&self, repo: &str, reference: &str, commit: &str) -> Result<()> {
+        let path = self.ref_path(repo, reference);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, commit).context("Failed to update template cache")?;
+        Ok(())
+    }
+
+    /// Lists the cached references of a repository
+    ///
+    /// The repository is looked up by its key, the same way as in
+    /// [`Self::lookup`], so keys with characters the cache directory names
+    /// can't hold (such as the `:` of a port) still match.
+    pub fn refs(&self, repo: &str) -> Result<Vec<String>> {
+        let refs_dir = self.repo_path(repo).join("refs");
+        if !refs_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut refs: Vec<String> = fs::read_dir(&refs_dir)?
+            .filter_map(|e| e.ok())
+            .map(|entry| decode_component(&entry.file_name().to_string_lossy()))
+            .collect();
+        refs.sort();
+        Ok(refs)
+    }
+
+    /// Adds a snapshot to the cache
+    ///
+    /// `fill` is called with an empty staging directory that it should
+    /// populate. The directory is only moved into place once `fill`
+    /// succeeds, so an interrupted download never leaves a partial snapshot.
+    ///
+    /// # Returns
+    ///
+    /// Returns the path of the stored snapshot
+    pub fn insert<F>(&self, repo: &str, reference: &str, commit: &str, fill: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&Path) -> Result<()>,
+    {
+        let target = self.snapshot_path(repo, commit);
+        let parent = target
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Invalid cache path"))?;
+        fs::create_dir_all(parent).context("Failed to create template cache")?;
+
+        let staging = tempfile::tempdir_in(parent).context("Failed to create template cache")?;
+        fill(staging.path())?;
+
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(staging.keep(), &target).context("Failed to store template in cache")?;
+
+        self.set_ref(repo, reference, commit)?;
+        Ok(target)
+    }
+
+    /// Lists all cached references
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+
+        for repo_dir in self.repo_dirs() {
+            let repo = self.repo_key(&repo_dir);
+            let refs_dir = repo_dir.join("refs");
+            if !refs_dir.is_dir() {
+                continue;
+            }
+
+            for entry in fs::read_dir(&refs_dir)?.filter_map(|e| e.ok()) {
+                let commit = fs::read_to_string(entry.path())?.trim().to_string();
+                entries.push(CacheEntry {
+                    repo: repo.clone(),
+                    reference: decode_component(&entry.file_name().to_string_lossy()),
+                    commit,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| (&a.repo, &a.reference).cmp(&(&b.repo, &b.reference)));
+        Ok(entries)
+    }
+
+    /// Removes snapshots that are no longer referenced by any branch
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of snapshots removed
+    pub fn prune(&self) -> Result<usize> {
+        let mut removed = 0;
+
+        for repo_dir in self.repo_dirs() {
+            let refs_dir = repo_dir.join("refs");
+            let mut live = HashSet::new();
+            if refs_dir.is_dir() {
+                for entry in fs::read_dir(&refs_dir)?.filter_map(|e| e.ok()) {
+                    let commit = fs::read_to_string(entry.path())?.trim().to_string();
+                    if self.snapshot_path_in(&repo_dir, &commit).is_dir() {
+                        live.insert(sanitize_component(&commit));
+                    } else {
+                        // Dangling reference, its snapshot is gone
+                        fs::remove_file(entry.path())?;
+                    }
+                }
+            }
+
+            for entry in fs::read_dir(repo_dir.join("snapshots"))?.filter_map(|e| e.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !live.contains(&name) {
+                    remove_path(&entry.path())?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes everything from the cache
+    pub fn clear(&self) -> Result<()> {
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root).context("Failed to clear template cache")?;
+        }
+        Ok(())
+    }
+
+    /// Finds every repository directory (one that holds a `snapshots` folder)
+    fn repo_dirs(&self) -> Vec<PathBuf> {
+        WalkDir::new(&self.root)
+            .into_iter()
+            // Never descend into refs or into the snapshots themselves
+            .filter_entry(|e| {
+                e.file_name() != "refs"
+                    && e.path()
+                        .parent()
+                        .is_none_or(|p| p.file_name() != Some("snapshots".as_ref()))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir() && e.file_name() == "snapshots")
+            .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+            .collect()
+    }
+
+    /// Rebuilds a repository key from its directory
+    fn repo_key(&self, repo_dir: &Path) -> String {
+        repo_dir
+            .strip_prefix(&self.root)
+            .unwrap_or(repo_dir)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn repo_path(&self, repo: &str) -> PathBuf {
+        repo.split('/')
+            .filter(|s| !s.is_empty())
+            .fold(self.root.clone(), |path, segment| {
+                path.join(sanitize_component(segment))
+            })
+    }
+
+    fn snapshot_path(&self, repo: &str, commit: &str) -> PathBuf {
+        self.snapshot_path_in(&self.repo_path(repo), commit)
+    }
+
+    fn snapshot_path_in(&self, repo_dir: &Path, commit: &str) -> PathBuf {
+        repo_dir.join("snapshots").join(sanitize_component(commit))
+    }
+
+    fn ref_path(&self, repo: &str, reference: &str) -> PathBuf {
+        self.repo_path(repo)
+            .join("refs")
+            .join(encode_component(reference))
+    }
+}
+
+/// Keeps a path segment to a safe character set
+fn sanitize_component(segment: &str) -> String {
+    let cleaned: String = segment
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match cleaned.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => cleaned,
+    }
+}
+
+/// Encodes a reference name (which may contain `/`) as a single file name
+fn encode_component(reference: &str) -> String {
+    let mut encoded = String::with_capacity(reference.len());
+    for c in reference.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') => encoded.push(c),
+            // A leading dot would hide the file or allow `..`
+            '.' if !encoded.is_empty() => encoded.push(c),
+            c => {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    encoded
+}
+
+/// Reverses [`encode_component`]
+fn decode_component(encoded: &str) -> String {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+
+    while let Some((&first, tail)) = rest.split_first() {
+        if first == b'%'
+            && tail.len() >= 2
+            && let Ok(byte) = u8::from_str_radix(&String::from_utf8_lossy(&tail[..2]), 16)
+        {
+            bytes.push(byte);
+            rest = &tail[2..];
+            continue;
+        }
+        bytes.push(first);
+        rest = tail;
+    }
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Removes a file or directory
+fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fill_with(content: &'static str) -> impl FnOnce(&Path) -> Result<()> {
+        move |dir| {
+            fs::write(dir.join("file.txt"), content)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_insert_and_lookup() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = TemplateCache::at(temp_dir.path().to_path_buf());
+        let repo = "github.com/user/repo";
+
+        assert!(cache.lookup(repo, "main").is_none());
+
+        let path = cache
+            .insert(repo, "main", "abc123", fill_with("hello"))
+            .unwrap();
+        assert_eq!(fs::read_to_string(path.join("file.txt")).unwrap(), "hello");
+
+        let (commit, snapshot) = cache.lookup(repo, "main").unwrap();
+        assert_eq!(commit, "abc123");
+        assert_eq!(snapshot, path);
+        assert_eq!(cache.snapshot(repo, "abc123"), Some(path));
+
+        // Keys with a port are found again
+        let repo = "git.example.com:8443/team/templates";
+        cache
+            .insert(repo, "v1.0.0", "abc123", fill_with("hello"))
+            .unwrap();
+        cache.set_ref(repo, "v1.1.0", "abc123").unwrap();
+        assert_eq!(cache.refs(repo).unwrap(), vec!["v1.0.0", "v1.1.0"]);
+        assert!(cache.refs("git.example.com/other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_insert_leaves_no_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = TemplateCache::at(temp_dir.path().to_path_buf());
+        let repo = "github.com/user/repo";
+
+        let result = cache.insert(repo, "main", "abc123", |_| anyhow::bail!("boom"));
+        assert!(result.is_err());
+        assert!(cache.snapshot(repo, "abc123").is_none());
+        assert!(cache.lookup(repo, "main").is_none());
+    }
+
+    #[test]
+    fn test_entries_and_prune() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = TemplateCache::at(temp_dir.path().to_path_buf());
+        let repo = "github.com/user/repo";
+
+        cache.insert(repo, "main", "old", fill_with("v1")).unwrap();
+        cache.insert(repo, "main", "new", fill_with("v2")).unwrap();
+        cache
+            .insert(repo, "feature/x", "feat", fill_with("v3"))
+            .unwrap();
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                CacheEntry {
+                    repo: repo.to_string(),
+                    reference: "feature/x".to_string(),
+                    commit: "feat".to_string(),
+                },
+                CacheEntry {
+                    repo: repo.to_string(),
+                    reference: "main".to_string(),
+                    commit: "new".to_string(),
+                },
+            ]
+        );
+
+        // "old" is no longer referenced by any branch
+        assert_eq!(cache.prune().unwrap(), 1);
+        assert!(cache.snapshot(repo, "old").is_none());
+        assert!(cache.snapshot(repo, "new").is_some());
+        assert!(cache.snapshot(repo, "feat").is_some());
+
+        cache.clear().unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_component_encoding() {
+        assert_eq!(sanitize_component("../etc"), ".._etc");
+        assert_eq!(sanitize_component(".."), "_");
+        assert_eq!(encode_component("feature/x"), "feature%2Fx");
+        assert_eq!(encode_component(".hidden"), "%2Ehidden");
+        assert_eq!(decode_component("feature%2Fx"), "feature/x");
+        assert_eq!(decode_component(&encode_component("a/b%c.d")), "a/b%c.d");
+    }
+}
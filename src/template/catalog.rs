@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::archive::extract_tarball;
+use super::cache::TemplateCache;
+use super::clone::clone_snapshot;
+use super::download::{DownloadOptions, download_manifests};
+use super::manifest::{MANIFEST_FILE, TemplateManifest};
+use super::source::TemplateSource;
+
+/// Most names suggested when a template isn't found
+const MAX_SUGGESTIONS: usize = 3;
+
+/// How similar a name has to be to be suggested (Jaro-Winkler, 0 to 1)
+const SIMILARITY_THRESHOLD: f64 = 0.7;
+
+/// A template found in a repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateInfo {
+    /// Directory name of the template
+    pub name: String,
+    /// Description from the template's manifest
+    pub description: Option<String>,
+}
+
+/// Lists the templates in a repository
+///
+/// Remote repositories are read from a fresh download of the archive, but
+/// only the manifests are extracted. Templates whose manifest can't be read
+/// are listed without a description.
+///
+/// # Arguments
+///
+/// * `source` - Repository to list
+/// * `reference` - Branch, tag or commit SHA for git repositories
+/// * `options` - Options for remote downloads
+///
+/// # Returns
+///
+/// Returns the templates sorted by name
+pub fn list_templates(
+    source: &TemplateSource,
+    reference: &str,
+    options: &DownloadOptions,
+) -> Result<Vec<TemplateInfo>> {
+    match source {
+        TemplateSource::Remote(url) => {
+            let temp_dir = tempfile::tempdir()?;
+            let names = download_manifests(url, reference, temp_dir.path(), options)?;
+            Ok(describe(temp_dir.path(), names))
+        }
+        TemplateSource::Git(url) => {
+            let (_, snapshot) = clone_snapshot(&TemplateCache::open()?, url, reference, options)?;
+            Ok(describe_dir(&snapshot))
+        }
+        TemplateSource::LocalRepo(dir) => Ok(describe_dir(dir)),
+        TemplateSource::LocalArchive(file) => {
+            let bytes = fs::read(file)
+                .with_context(|| format!("Failed to read archive '{}'", file.display()))?;
+            let temp_dir = tempfile::tempdir()?;
+            extract_tarball(&bytes, temp_dir.path())?;
+            Ok(describe_dir(&repository_root(temp_dir.path())))
+        }
+        TemplateSource::LocalTemplate(dir) => Ok(describe(
+            dir.parent().unwrap_or(Path::new(".")),
+            vec![
+                dir.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            ],
+        )),
+    }
+}
+
+/// Lists the templates in a repository directory
+fn describe_dir(repo_dir: &Path) -> Vec<TemplateInfo> {
+    describe(repo_dir, template_names(repo_dir))
+}
+
+/// Reads the description of each template
+fn describe(repo_dir: &Path, names: Vec<String>) -> Vec<TemplateInfo> {
+    names
+        .into_iter()
+        .map(|name| TemplateInfo {
+            description: TemplateManifest::load(&repo_dir.join(&name))
+                .ok()
+                .and_then(|manifest| manifest.description),
+            name,
+        })
+        .collect()
+}
+
+/// Returns the directory holding the templates of an extracted repository
+///
+/// Archives usually wrap the repository in a single top-level directory.
+pub fn repository_root(dir: &Path) -> PathBuf {
+    let entries: Vec<_> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).collect())
+        .unwrap_or_default();
+
+    match entries.as_slice() {
+        [only] if only.path().is_dir() && !only.path().join(MANIFEST_FILE).exists() => only.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// Names of the template directories in a repository
+///
+/// Every directory that isn't hidden counts as a template.
+pub fn template_names(repo_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(repo_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| !name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Returns `true` if a template name is a single directory name
+pub fn is_template_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+/// Builds the error for a template that isn't in a repository
+///
+/// Suggests the closest names, or lists what is available when none is
+/// close.
+pub fn not_found_error(template: &str, available: &[String]) -> anyhow::Error {
+    let suggestions = suggest(template, available);
+    let hint = if !suggestions.is_empty() {
+        let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+        format!("Did you mean {}?", quoted.join(" or "))
+    } else if !available.is_empty() {
+        format!("Available templates: {}.", available.join(", "))
+    } else {
+        "Templates should be directories in the root of the repository.".to_string()
+    };
+
+    anyhow::anyhow!(
+        "Template '{}' not found in repository. {} Run `wenzetu templates list` to see all templates.",
+        template,
+        hint
+    )
+}
+
+/// Returns the names closest to `name`, best match first
+fn suggest<'a>(name: &str, candidates: &'a [String]) -> Vec<&'a str> {
+    let name = name.to_lowercase();
+    let mut scored: Vec<(f64, &str)> = candidates
+        .iter()
+        .map(|candidate| {
+            let score = strsim::jaro_winkler(&name, &candidate.to_lowercase());
+            (score, candidate.as_str())
+        })
+        .filter(|(score, _)| *score >= SIMILARITY_THRESHOLD)
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_not_found_suggestions() {
+        let available: Vec<String> = ["api", "default", "fullstack"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(suggest("defualt", &available), vec!["default"]);
+        assert_eq!(suggest("Full-stack", &available), vec!["fullstack"]);
+        assert!(suggest("zzz", &available).is_empty());
+
+        let err = not_found_error("defualt", &available).to_string();
+        assert!(err.contains("Did you mean 'default'?"));
+        let err = not_found_error("zzz", &available).to_string();
+        assert!(err.contains("Available templates: api, default, fullstack."));
+    }
+
+    #[test]
+    fn test_list_local_repository() {
+        let repo = TempDir::new().unwrap();
+        fs::create_dir_all(repo.path().join("default")).unwrap();
+        fs::write(
+            repo.path().join("default/template.toml"),
+            "description = \"Minimal web app\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(repo.path().join("api/src")).unwrap();
+        fs::create_dir_all(repo.path().join(".github")).unwrap();
+        fs::write(repo.path().join("README.md"), "templates").unwrap();
+
+        let source = TemplateSource::LocalRepo(repo.path().to_path_buf());
+        let templates = list_templates(&source, "main", &DownloadOptions::default()).unwrap();
+        assert_eq!(
+            templates,
+            vec![
+                TemplateInfo {
+                    name: "api".to_string(),
+                    description: None,
+                },
+                TemplateInfo {
+                    name: "default".to_string(),
+                    description: Some("Minimal web app".to_string()),
+                },
+            ]
+        );
+    }
+}
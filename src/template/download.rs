@@ -1,152 +1,502 @@
 use anyhow::{Context, Result};
+use colored::*;
+use indicatif::{HumanBytes, ProgressBar};
+use reqwest::blocking::Response;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::utils::copy_dir_recursively;
+use super::archive::{ExtractLimits, ProgressReader, extract_manifests, extract_template};
+use super::cache::TemplateCache;
+use super::catalog::{is_template_name, not_found_error, template_names};
+use super::host::RemoteRepo;
+use super::verify::{ArchiveVerifier, Verification};
+use crate::utils::config::{Config, config_path};
+use crate::utils::http::{rate_limit, send};
+use crate::utils::http_client;
 
-/// Downloads a template from a GitHub repository
+/// Options controlling how templates are fetched
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Only use the local cache, never touch the network
+    pub offline: bool,
+    /// Ignore cached snapshots and download again
+    pub refresh: bool,
+    /// Git host provider, when it can't be detected from the URL
+    pub provider: Option<String>,
+    /// Overrides the provider's API base URL
+    pub api_url: Option<String>,
+    /// Spinner showing download progress
+    pub progress: Option<ProgressBar>,
+    /// Checksum and signature the archive has to match
+    pub verify: Verification,
+    /// Host of the repository given with `--repo`, which `$WENZETU_TOKEN` is
+    /// sent to and the only git host a template's `extends` may clone from
+    pub repo_host: Option<String>,
+}
+
+/// Downloads a template from a remote git repository
+///
+/// The host (GitHub, GitLab, Gitea, Bitbucket) is detected from the URL or
+/// taken from `options.provider`. Extracted archives are kept in the local
+/// [`TemplateCache`], keyed by repository, reference and commit, so later
+/// runs (and `--offline` runs) can reuse them. Private repositories are
+/// read with the token found by [`RemoteRepo::token`].
+///
+/// When `options.verify` asks for a checksum or signature, the archive is
+/// always downloaded again, since the cache only holds extracted files.
 ///
 /// # Arguments
 ///
-/// * `repo_url` - Full GitHub repository URL or shorthand (username/repo)
-/// * `branch` - Branch name to download from
+/// * `repo_url` - Full repository URL
+/// * `reference` - Branch, tag or commit SHA to download
 /// * `template` - Template name (directory name in the repo)
-/// * `dest` - Destination path where the template should be extracted
+/// * `options` - Host, cache and network options
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` if successful, or an error if download fails
-pub fn download_template(repo_url: &str, branch: &str, template: &str, dest: &Path) -> Result<()> {
+/// Returns the commit the template was taken from and the template
+/// directory inside the cached snapshot, or an error if download fails
+pub fn download_template(
+    repo_url: &str,
+    reference: &str,
+    template: &str,
+    options: &DownloadOptions,
+) -> Result<(String, PathBuf)> {
     // Parse repository information
-    let (owner, repo) = parse_repo_url(repo_url)?;
+    let repo = RemoteRepo::parse(
+        repo_url,
+        options.provider.as_deref(),
+        options.api_url.as_deref(),
+    )?;
 
-    // GitHub API URL to get the tarball
-    let tarball_url = format!(
-        "https://api.github.com/repos/{}/{}/tarball/{}",
-        owner, repo, branch
-    );
+    let token = repo.token(&Config::load()?, options.repo_host.as_deref());
+
+    let mut options = options.clone();
+    if options.verify.is_required() {
+        if options.offline {
+            anyhow::bail!("Template checksums and signatures can't be verified with --offline");
+        }
+        options.refresh = true;
+    }
 
-    // Download tarball
-    let bytes = download_tarball(&tarball_url)?;
+    // Get the extracted template, from the cache when possible
+    let cache = TemplateCache::open()?;
+    fetch_remote_template(
+        &cache,
+        &repo,
+        reference,
+        template,
+        token.as_deref(),
+        &options,
+    )
+}
 
-    // Extract to temporary directory
-    let temp_dir = tempfile::tempdir()?;
-    extract_tarball(&bytes, temp_dir.path())?;
+/// Returns a template from the cached snapshot of a repository reference,
+/// downloading it first if needed
+///
+/// Snapshots only hold the templates that were asked for. A template that
+/// isn't in the snapshot yet is downloaded and added to it.
+fn fetch_remote_template(
+    cache: &TemplateCache,
+    repo: &RemoteRepo,
+    reference: &str,
+    template: &str,
+    token: Option<&str>,
+    options: &DownloadOptions,
+) -> Result<(String, PathBuf)> {
+    let (commit, snapshot) = cached_snapshot(
+        cache,
+        &repo.cache_key(),
+        reference,
+        options,
+        || resolve_commit(repo, reference, token),
+        |commit, dir| download_archive(repo, commit, token, template, dir, options),
+    )?;
 
-    // Find and copy the template
-    copy_template_to_dest(temp_dir.path(), template, dest)?;
+    match find_template_dir(&snapshot, template) {
+        Ok(template_dir) => Ok((commit, template_dir)),
+        Err(e) if options.offline => Err(e),
+        Err(_) => {
+            // Extract next to the snapshot, then move the template in place
+            let staging = tempfile::tempdir_in(&snapshot)?;
+            download_archive(repo, &commit, token, template, staging.path(), options)?;
 
-    Ok(())
+            let template_dir = snapshot.join(template);
+            if let Some(parent) = template_dir.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if !template_dir.exists() {
+                fs::rename(staging.path().join(template), &template_dir)
+                    .context("Failed to add template to the cache")?;
+            }
+            Ok((commit, template_dir))
+        }
+    }
 }
 
-/// Parses a GitHub repository URL or shorthand into owner and repo name
+/// Returns the cached snapshot of a repository reference, fetching it first
+/// if needed
+///
+/// `resolve` maps the reference to a commit and `fill` populates an empty
+/// snapshot directory for that commit. A full commit SHA is immutable, so a
+/// cached snapshot of it is used without resolving anything. When the
+/// reference cannot be resolved (e.g. no network) and a cached copy exists,
+/// the cached copy is used with a warning.
 ///
 /// # Arguments
 ///
-/// * `repo_url` - GitHub repository URL or shorthand
+/// * `cache` - Template cache
+/// * `repo_key` - Key identifying the repository in the cache
+/// * `reference` - Branch, tag or commit SHA
+/// * `options` - Cache and network options
+/// * `resolve` - Resolves the reference to a commit
+/// * `fill` - Writes the repository contents at a commit into a directory
 ///
 /// # Returns
 ///
-/// Returns a tuple of (owner, repo) or an error if parsing fails
+/// Returns the resolved commit and the path of the snapshot directory
+pub fn cached_snapshot<R, F>(
+    cache: &TemplateCache,
+    repo_key: &str,
+    reference: &str,
+    options: &DownloadOptions,
+    resolve: R,
+    fill: F,
+) -> Result<(String, PathBuf)>
+where
+    R: FnOnce() -> Result<String>,
+    F: FnOnce(&str, &Path) -> Result<()>,
+{
+    if is_full_sha(reference)
+        && !options.refresh
+        && let Some(snapshot) = cache.snapshot(repo_key, reference)
+    {
+        return Ok((reference.to_string(), snapshot));
+    }
+
+    if options.offline {
+        return cache.lookup(repo_key, reference).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Template repository '{}' (ref '{}') is not in the local cache. Run once without --offline to cache it.",
+                repo_key,
+                reference
+            )
+        });
+    }
+
+    let commit = match resolve() {
+        Ok(commit) => commit,
+        Err(e) => match cache.lookup(repo_key, reference) {
+            Some((commit, snapshot)) if !options.refresh => {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Warning: could not reach {}, using cached template ({})",
+                        repo_key,
+                        short_commit(&commit)
+                    )
+                    .yellow()
+                );
+                return Ok((commit, snapshot));
+            }
+            _ => return Err(e),
+        },
+    };
+
+    if !options.refresh
+        && let Some(snapshot) = cache.snapshot(repo_key, &commit)
+    {
+        cache.set_ref(repo_key, reference, &commit)?;
+        return Ok((commit, snapshot));
+    }
+
+    let snapshot = cache.insert(repo_key, reference, &commit, |dir| fill(&commit, dir))?;
+    Ok((commit, snapshot))
+}
+
+/// Returns `true` if a reference is a full 40-character commit SHA
+pub fn is_full_sha(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Resolves a branch, tag or (short) commit SHA to a full commit SHA
+///
+/// # Arguments
 ///
-/// # Examples
+/// * `repo` - Remote repository
+/// * `reference` - Branch, tag or commit SHA
+/// * `token` - Optional access token
 ///
-/// ```
-/// let (owner, repo) = parse_repo_url("https://github.com/user/repo").unwrap();
-/// assert_eq!(owner, "user");
-/// assert_eq!(repo, "repo");
+/// # Returns
 ///
-/// let (owner, repo) = parse_repo_url("user/repo").unwrap();
-/// assert_eq!(owner, "user");
-/// assert_eq!(repo, "repo");
-/// ```
-fn parse_repo_url(repo_url: &str) -> Result<(&str, &str)> {
-    let parts: Vec<&str> = repo_url.trim_end_matches('/').split('/').collect();
+/// Returns the full commit SHA
+fn resolve_commit(repo: &RemoteRepo, reference: &str, token: Option<&str>) -> Result<String> {
+    let request = repo.commit_request(reference);
 
-    if parts.len() >= 2 {
-        let owner = parts[parts.len() - 2];
-        let repo = parts[parts.len() - 1];
-        Ok((owner, repo))
-    } else {
-        anyhow::bail!("Invalid repository URL: {}", repo_url);
+    let response =
+        send(authorized_get(repo, &request.url, token)?.header("Accept", request.accept))
+            .context("Failed to resolve template ref")?;
+
+    if !response.status().is_success() {
+        return Err(status_error(
+            &format!("Failed to resolve ref '{}'", reference),
+            &response,
+            token.is_some(),
+        ));
     }
+
+    repo.parse_commit(&response.text()?)
+        .with_context(|| format!("Unexpected response while resolving ref '{}'", reference))
 }
 
-/// Downloads a tarball from GitHub API
+/// Most pages of tags read from a git host
+const MAX_TAG_PAGES: u32 = 10;
+
+/// Lists the tags of a remote repository
 ///
 /// # Arguments
 ///
-/// * `tarball_url` - GitHub API tarball URL
+/// * `repo_url` - Full repository URL
+/// * `options` - Host options
 ///
 /// # Returns
 ///
-/// Returns the downloaded bytes or an error
-fn download_tarball(tarball_url: &str) -> Result<Vec<u8>> {
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("unc-cli")
-        .build()?;
+/// Returns the tag names, or an error if the host can't be reached
+pub fn list_tags(repo_url: &str, options: &DownloadOptions) -> Result<Vec<String>> {
+    let repo = RemoteRepo::parse(
+        repo_url,
+        options.provider.as_deref(),
+        options.api_url.as_deref(),
+    )?;
+    let token = repo.token(&Config::load()?, options.repo_host.as_deref());
 
-    let response = client
-        .get(tarball_url)
-        .send()
-        .context("Failed to download template")?;
+    let mut tags = Vec::new();
+    for page in 1..=MAX_TAG_PAGES {
+        let request = repo.tags_request(page);
+        let response = send(
+            authorized_get(&repo, &request.url, token.as_deref())?.header("Accept", request.accept),
+        )
+        .context("Failed to list template versions")?;
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Failed to download template: HTTP {}. Make sure the repository and branch exist.",
-            response.status()
+        if !response.status().is_success() {
+            return Err(status_error(
+                "Failed to list template versions",
+                &response,
+                token.is_some(),
+            ));
+        }
+
+        let names = repo.parse_tags(&response.text()?)?;
+        if names.is_empty() {
+            break;
+        }
+        tags.extend(names);
+    }
+
+    Ok(tags)
+}
+
+/// Shortens a commit id for display
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+/// Starts a GET request, authenticated when a token is available
+fn authorized_get(
+    repo: &RemoteRepo,
+    url: &str,
+    token: Option<&str>,
+) -> Result<reqwest::blocking::RequestBuilder> {
+    let mut request = http_client()?.get(url);
+    if let Some(token) = token {
+        let (name, value) = repo.auth_header(token);
+        request = request.header(name, value);
+    }
+    Ok(request)
+}
+
+/// Builds the error for a failed request, with a hint about the likely cause
+///
+/// Private repositories show up as 401, 403 or 404 when no token is sent.
+/// Rate limits show up as 403 or 429 and report when they reset.
+fn status_error(message: &str, response: &Response, authenticated: bool) -> anyhow::Error {
+    let status = response.status();
+    let auth_hint = format!(
+        "Set WENZETU_TOKEN (or GITHUB_TOKEN for GitHub), or add a token under [credentials.\"<host>\"] in {}",
+        config_path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "the wenzetu config file".to_string())
+    );
+
+    if let Some(limit) = rate_limit(response) {
+        let hint = if authenticated {
+            "Wait for it to reset and try again.".to_string()
+        } else {
+            format!("{} for a higher limit.", auth_hint)
+        };
+        return anyhow::anyhow!(
+            "{}: rate limit exceeded (HTTP {}). The limit resets {}. {}",
+            message,
+            status,
+            limit.describe_reset(),
+            hint
         );
     }
 
-    let bytes = response.bytes()?.to_vec();
-    Ok(bytes)
+    let hint = match status.as_u16() {
+        401 | 403 | 404 if !authenticated => {
+            format!("The repository may be private. {}.", auth_hint)
+        }
+        401 | 403 => {
+            "The access token was rejected. Make sure it is valid and can read the repository."
+                .to_string()
+        }
+        500.. => "The server had a problem. Try again later.".to_string(),
+        _ => "Make sure the repository and ref exist.".to_string(),
+    };
+
+    anyhow::anyhow!("{}: HTTP {}. {}", message, status, hint)
 }
 
-/// Extracts a gzipped tarball to a destination directory
+/// Streams a repository archive from a git host and extracts one template
+///
+/// The archive is never held in memory or written to disk as a whole.
+/// Only entries under `<root>/<template>/` are extracted, into
+/// `dest/<template>`. The spinner in `options.progress` shows the bytes
+/// received. The archive is checked against `options.verify` once it has
+/// been read; on a mismatch an error is returned and nothing extracted
+/// should be used.
 ///
 /// # Arguments
 ///
-/// * `bytes` - Tarball bytes
-/// * `dest` - Destination directory path
-fn extract_tarball(bytes: &[u8], dest: &Path) -> Result<()> {
-    let tar = flate2::read::GzDecoder::new(bytes);
-    let mut archive = tar::Archive::new(tar);
-    archive.unpack(dest).context("Failed to extract tarball")?;
-    Ok(())
+/// * `repo` - Remote repository
+/// * `commit` - Commit to download
+/// * `token` - Optional access token
+/// * `template` - Template name (directory name in the repo)
+/// * `dest` - Destination directory
+/// * `options` - Download options
+fn download_archive(
+    repo: &RemoteRepo,
+    commit: &str,
+    token: Option<&str>,
+    template: &str,
+    dest: &Path,
+    options: &DownloadOptions,
+) -> Result<()> {
+    let signature = options.verify.trusted_signature()?;
+    let verifier = ArchiveVerifier::new(&options.verify, signature.as_ref())?;
+
+    let limits = ExtractLimits::default();
+    let response = request_archive(repo, commit, token, &limits)?;
+    let total = response.content_length();
+    let progress = ProgressReader::new(response, |received| {
+        if let Some(pb) = &options.progress {
+            pb.set_message(progress_message(received, total));
+        }
+    });
+    let mut reader = verifier.reader(progress);
+    extract_template(&mut reader, template, dest, &limits)?;
+    reader.finish(limits.max_compressed)
 }
 
-/// Copies a template from the extracted directory to the destination
+/// Downloads a repository archive and extracts only the template manifests
+///
+/// Used to list the templates in a repository without extracting them.
 ///
 /// # Arguments
 ///
-/// * `extracted_dir` - Directory where tarball was extracted
-/// * `template` - Template name to find
-/// * `dest` - Final destination directory
-fn copy_template_to_dest(extracted_dir: &Path, template: &str, dest: &Path) -> Result<()> {
-    // Find the template directory
-    let extracted_dirs: Vec<_> = fs::read_dir(extracted_dir)?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_dir())
-        .collect();
+/// * `repo_url` - Full repository URL
+/// * `reference` - Branch, tag or commit SHA
+/// * `dest` - Destination directory, receives `<template>/template.toml`
+/// * `options` - Host options
+///
+/// # Returns
+///
+/// Returns the names of the templates in the repository
+pub fn download_manifests(
+    repo_url: &str,
+    reference: &str,
+    dest: &Path,
+    options: &DownloadOptions,
+) -> Result<Vec<String>> {
+    let repo = RemoteRepo::parse(
+        repo_url,
+        options.provider.as_deref(),
+        options.api_url.as_deref(),
+    )?;
+    let token = repo.token(&Config::load()?, options.repo_host.as_deref());
+    let token = token.as_deref();
+
+    let commit = resolve_commit(&repo, reference, token)?;
+    let limits = ExtractLimits::default();
+    let response = request_archive(&repo, &commit, token, &limits)?;
+    extract_manifests(response, dest, &limits)
+}
+
+/// Starts downloading the archive of a commit
+///
+/// Archives whose declared size is over the limit are refused before any
+/// of them is read.
+fn request_archive(
+    repo: &RemoteRepo,
+    commit: &str,
+    token: Option<&str>,
+    limits: &ExtractLimits,
+) -> Result<Response> {
+    let response = send(authorized_get(repo, &repo.archive_url(commit), token)?)
+        .context("Failed to download template")?;
 
-    if extracted_dirs.is_empty() {
-        anyhow::bail!("No directories found in downloaded template");
+    if !response.status().is_success() {
+        return Err(status_error(
+            "Failed to download template",
+            &response,
+            token.is_some(),
+        ));
     }
 
-    let template_base = &extracted_dirs[0].path();
-    let template_path = template_base.join(template);
+    if response
+        .content_length()
+        .is_some_and(|length| length > limits.max_compressed)
+    {
+        return Err(limits.compressed_error());
+    }
 
-    if !template_path.exists() {
-        anyhow::bail!(
-            "Template '{}' not found in repository. Available templates should be in the root directory.",
-            template
-        );
+    Ok(response)
+}
+
+/// Formats download progress, e.g. `downloading 1.20 MiB / 3.40 MiB`
+fn progress_message(received: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) => format!(
+            "downloading {} / {}",
+            HumanBytes(received),
+            HumanBytes(total)
+        ),
+        None => format!("downloading {}", HumanBytes(received)),
     }
+}
 
-    // Copy template files to destination
-    copy_dir_recursively(&template_path, dest)?;
+/// Finds a template in a repository
+///
+/// Templates are the directories at the top level of `repo_dir`, which is a
+/// cached snapshot, a clone or the [`repository_root`] of an extracted
+/// archive.
+///
+/// # Arguments
+///
+/// * `repo_dir` - Directory holding the repository's templates
+/// * `template` - Template name to find
+///
+/// # Returns
+///
+/// Returns the path of the template directory
+pub fn find_template_dir(repo_dir: &Path, template: &str) -> Result<PathBuf> {
+    let template_path = repo_dir.join(template);
+    if !is_template_name(template) || !template_path.is_dir() {
+        return Err(not_found_error(template, &template_names(repo_dir)));
+    }
 
-    Ok(())
+    Ok(template_path)
 }
 
 /// Converts a repository shorthand to a full GitHub URL
@@ -179,26 +529,199 @@ pub fn normalize_repo_url(repo: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{StubResponse, StubServer, tarball};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fetch_remote_template_uses_cache() {
+        let archive = tarball(&[
+            (
+                "user-repo-abc123/default/Cargo.toml",
+                "name = \"{{project_name}}\"",
+            ),
+            ("user-repo-abc123/other/Cargo.toml", "other"),
+        ]);
+        let server = StubServer::with_routes(vec![
+            ("/repos/user/repo/commits/main", StubResponse::ok("abc123")),
+            ("/repos/user/repo/tarball/abc123", StubResponse::ok(archive)),
+        ]);
+
+        let cache_dir = TempDir::new().unwrap();
+        let cache = TemplateCache::at(cache_dir.path().to_path_buf());
+        let repo = RemoteRepo::parse(
+            "https://github.com/user/repo",
+            None,
+            Some(&format!("{}/", server.url())),
+        )
+        .unwrap();
+        let options = DownloadOptions::default();
+
+        let fetch = |template, options: &DownloadOptions| {
+            fetch_remote_template(&cache, &repo, "main", template, None, options)
+        };
+
+        let (commit, template_dir) = fetch("default", &options).unwrap();
+        assert_eq!(commit, "abc123");
+        assert!(template_dir.join("Cargo.toml").exists());
+        // Only the selected template is extracted
+        assert!(!template_dir.parent().unwrap().join("other").exists());
+
+        // Second run only resolves the ref, the archive comes from the cache
+        fetch("default", &options).unwrap();
+        let tarball_requests = || {
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.path.contains("/tarball/"))
+                .count()
+        };
+        assert_eq!(tarball_requests(), 1);
+
+        // Another template of the same commit is added to the snapshot
+        let (_, other_dir) = fetch("other", &options).unwrap();
+        assert!(other_dir.join("Cargo.toml").exists());
+        assert_eq!(tarball_requests(), 2);
+        assert!(fetch("missing", &options).is_err());
+
+        // Offline runs never touch the server
+        drop(server);
+        let offline = DownloadOptions {
+            offline: true,
+            ..Default::default()
+        };
+        assert_eq!(fetch("default", &offline).unwrap(), (commit, template_dir));
+        assert!(fetch_remote_template(&cache, &repo, "other", "default", None, &offline).is_err());
+    }
+
+    #[test]
+    fn test_verify_archive_checksum() {
+        use sha2::{Digest, Sha256};
+
+        let archive = tarball(&[("user-repo-abc123/default/Cargo.toml", "[package]")]);
+        let checksum: String = Sha256::digest(&archive)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let server = StubServer::with_routes(vec![
+            ("/repos/user/repo/commits/main", StubResponse::ok("abc123")),
+            ("/repos/user/repo/tarball/abc123", StubResponse::ok(archive)),
+        ]);
+
+        let cache_dir = TempDir::new().unwrap();
+        let cache = TemplateCache::at(cache_dir.path().to_path_buf());
+        let repo =
+            RemoteRepo::parse("https://github.com/user/repo", None, Some(&server.url())).unwrap();
+        let with_checksum = |sha256: &str| DownloadOptions {
+            verify: Verification {
+                sha256: Some(sha256.to_string()),
+                signature: None,
+            },
+            ..Default::default()
+        };
+
+        let err = fetch_remote_template(
+            &cache,
+            &repo,
+            "main",
+            "default",
+            None,
+            &with_checksum(&"0".repeat(64)),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+        // Nothing from the rejected archive is cached
+        assert!(cache.snapshot(&repo.cache_key(), "abc123").is_none());
+
+        let (_, template_dir) = fetch_remote_template(
+            &cache,
+            &repo,
+            "main",
+            "default",
+            None,
+            &with_checksum(&checksum),
+        )
+        .unwrap();
+        assert!(template_dir.join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_private_repository() {
+        let server = StubServer::start(|request| {
+            match request.headers.get("authorization").map(String::as_str) {
+                Some("Bearer secret") => StubResponse::ok("abc123"),
+                _ => StubResponse::status(404),
+            }
+        });
+        let repo = RemoteRepo::parse("https://github.com/user/private", None, Some(&server.url()))
+            .unwrap();
+
+        let err = resolve_commit(&repo, "main", None).unwrap_err();
+        assert!(err.to_string().contains("may be private"));
+
+        assert_eq!(
+            resolve_commit(&repo, "main", Some("secret")).unwrap(),
+            "abc123"
+        );
+
+        let err = resolve_commit(&repo, "main", Some("wrong")).unwrap_err();
+        assert!(!err.to_string().contains("may be private"));
+    }
+
+    #[test]
+    fn test_token_not_sent_to_other_hosts() {
+        let server = StubServer::start(|_| StubResponse::ok("[{\"sha\": \"abc123\"}]"));
+        let repo = RemoteRepo::parse(
+            "https://git.other.example/team/repo",
+            Some("gitea"),
+            Some(&server.url()),
+        )
+        .unwrap();
+        let env = |name: &str| (name == "WENZETU_TOKEN").then(|| "secret".to_string());
+
+        let token = repo.token_with(&Config::default(), Some("git.example.com"), env);
+        assert_eq!(
+            resolve_commit(&repo, "main", token.as_deref()).unwrap(),
+            "abc123"
+        );
+        let token = repo.token_with(&Config::default(), Some("git.other.example"), env);
+        resolve_commit(&repo, "main", token.as_deref()).unwrap();
+
+        let requests = server.requests();
+        assert!(!requests[0].headers.contains_key("authorization"));
+        assert_eq!(
+            requests[1].headers.get("authorization").map(String::as_str),
+            Some("token secret")
+        );
+    }
 
     #[test]
-    fn test_parse_repo_url() {
-        let (owner, repo) = parse_repo_url("https://github.com/user/repo").unwrap();
-        assert_eq!(owner, "user");
-        assert_eq!(repo, "repo");
+    fn test_rate_limited() {
+        let server = StubServer::start(|_| {
+            StubResponse::status(403)
+                .header("x-ratelimit-remaining", "0")
+                .header("x-ratelimit-reset", "1700000000")
+        });
+        let repo =
+            RemoteRepo::parse("https://github.com/user/repo", None, Some(&server.url())).unwrap();
 
-        let (owner, repo) = parse_repo_url("user/repo").unwrap();
-        assert_eq!(owner, "user");
-        assert_eq!(repo, "repo");
+        let err = resolve_commit(&repo, "main", None).unwrap_err().to_string();
+        assert!(err.contains("rate limit exceeded"));
+        assert!(err.contains("resets at 22:13 UTC"));
+        assert!(err.contains("WENZETU_TOKEN"));
+        assert!(!err.contains("may be private"));
 
-        let (owner, repo) = parse_repo_url("https://github.com/org/my-repo/").unwrap();
-        assert_eq!(owner, "org");
-        assert_eq!(repo, "my-repo");
+        let err = resolve_commit(&repo, "main", Some("secret"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Wait for it to reset"));
     }
 
     #[test]
-    fn test_parse_repo_url_invalid() {
-        assert!(parse_repo_url("invalid").is_err());
-        assert!(parse_repo_url("").is_err());
+    fn test_is_full_sha() {
+        assert!(is_full_sha("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_full_sha("0123456"));
+        assert!(!is_full_sha("main"));
+        assert!(!is_full_sha("0123456789abcdef0123456789abcdef0123456z"));
     }
 
     #[test]
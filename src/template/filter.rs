@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Decides which template paths are left out of a generated project, and
+/// which are copied without rendering
+///
+/// Patterns are globs relative to the template directory, matched against
+/// the path before names are rendered. `*` stays within one directory,
+/// `**` crosses directories, so `docker/**` matches everything under
+/// `docker/`.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    excluded: GlobSet,
+    verbatim: GlobSet,
+}
+
+impl PathFilter {
+    /// Creates a filter excluding paths that match any of the patterns
+    ///
+    /// # Returns
+    ///
+    /// Returns the filter, or an error naming a malformed pattern
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        Ok(Self {
+            excluded: glob_set(patterns)?,
+            verbatim: GlobSet::empty(),
+        })
+    }
+
+    /// Copies paths matching any of the patterns without rendering them
+    ///
+    /// # Returns
+    ///
+    /// Returns the filter, or an error naming a malformed pattern
+    pub fn with_verbatim<S: AsRef<str>>(mut self, patterns: &[S]) -> Result<Self> {
+        self.verbatim = glob_set(patterns)?;
+        Ok(self)
+    }
+
+    /// Returns `true` if a path, or one of its parent directories, is excluded
+    pub fn is_excluded(&self, relative_path: &Path) -> bool {
+        matches(&self.excluded, relative_path)
+    }
+
+    /// Returns `true` if a file's content is copied as it is
+    pub fn is_verbatim(&self, relative_path: &Path) -> bool {
+        matches(&self.verbatim, relative_path)
+    }
+}
+
+/// Returns `true` if a path, or one of its parent directories, matches
+fn matches(set: &GlobSet, relative_path: &Path) -> bool {
+    relative_path
+        .ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .any(|path| set.is_match(path))
+}
+
+/// Compiles a list of glob patterns
+fn glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(glob(pattern.as_ref())?);
+    }
+    builder.build().context("Failed to build file patterns")
+}
+
+/// Compiles a single glob pattern
+fn glob(pattern: &str) -> Result<Glob> {
+    GlobBuilder::new(pattern.trim_start_matches('/'))
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid file pattern '{}'", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_excluded() {
+        let filter = PathFilter::new(&["docker/**", "*.md", "ci"]).unwrap();
+
+        assert!(filter.is_excluded(Path::new("docker/Dockerfile")));
+        assert!(filter.is_excluded(Path::new("README.md")));
+        assert!(filter.is_excluded(Path::new("ci/build.yml")));
+        assert!(!filter.is_excluded(Path::new("docs/guide.md")));
+        assert!(!filter.is_excluded(Path::new("src/main.rs")));
+
+        assert!(PathFilter::new(&["a[b"]).is_err());
+    }
+
+    #[test]
+    fn test_is_verbatim() {
+        let filter = PathFilter::new(&["docs/**"])
+            .unwrap()
+            .with_verbatim(&["static/vendor", "**/*.min.js"])
+            .unwrap();
+
+        assert!(filter.is_verbatim(Path::new("static/vendor/htmx.js")));
+        assert!(filter.is_verbatim(Path::new("assets/app.min.js")));
+        assert!(!filter.is_verbatim(Path::new("assets/app.js")));
+        assert!(!filter.is_excluded(Path::new("static/vendor/htmx.js")));
+    }
+}
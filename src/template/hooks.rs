@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use colored::*;
+use dialoguer::Confirm;
+use std::path::Path;
+use std::process::Command;
+
+/// Whether to run the commands a template declares after generation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HookPolicy {
+    /// Show the commands and ask first
+    #[default]
+    Ask,
+    /// Run without asking (`--trust`)
+    Trust,
+    /// Never run them (`--no-hooks`)
+    Skip,
+}
+
+/// Runs a template's post-generation hooks in the project directory
+///
+/// Hooks come from a possibly remote template, so the exact commands are
+/// shown and confirmed before anything runs, unless they are trusted
+/// up front.
+///
+/// # Arguments
+///
+/// * `commands` - Rendered hook commands, run in order with the system shell
+/// * `project_path` - Directory the commands run in
+/// * `policy` - Whether to ask, run or skip
+/// * `interactive` - Whether a confirmation can be asked on the terminal
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the hooks ran or were skipped, or an error if a hook
+/// fails or untrusted hooks can't be confirmed
+pub fn run_hooks(
+    commands: &[String],
+    project_path: &Path,
+    policy: HookPolicy,
+    interactive: bool,
+) -> Result<()> {
+    if commands.is_empty() || policy == HookPolicy::Skip {
+        return Ok(());
+    }
+
+    println!("{}", "The template wants to run:".bold());
+    for command in commands {
+        println!("  {}", command.cyan());
+    }
+
+    if policy == HookPolicy::Ask {
+        if !interactive {
+            anyhow::bail!(
+                "The template declares post-generation hooks. Pass --trust to run them or --no-hooks to skip them."
+            );
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt("Run these commands?")
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!("{}", "Skipped template hooks".yellow());
+            return Ok(());
+        }
+    }
+
+    for command in commands {
+        run_hook(command, project_path)?;
+    }
+
+    Ok(())
+}
+
+/// Runs one hook with the system shell
+fn run_hook(command: &str, project_path: &Path) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+
+    let status = shell
+        .current_dir(project_path)
+        .status()
+        .with_context(|| format!("Failed to run hook '{}'", command))?;
+
+    if !status.success() {
+        anyhow::bail!("Hook '{}' failed with {}", command, status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let commands = vec!["echo generated > hook.txt".to_string()];
+
+        // Untrusted hooks can't run without a terminal to confirm them
+        assert!(run_hooks(&commands, temp_dir.path(), HookPolicy::Ask, false).is_err());
+
+        run_hooks(&commands, temp_dir.path(), HookPolicy::Skip, false).unwrap();
+        assert!(!temp_dir.path().join("hook.txt").exists());
+
+        run_hooks(&commands, temp_dir.path(), HookPolicy::Trust, false).unwrap();
+        assert!(temp_dir.path().join("hook.txt").exists());
+
+        let failing = vec!["exit 3".to_string()];
+        let err = run_hooks(&failing, temp_dir.path(), HookPolicy::Trust, false).unwrap_err();
+        assert!(err.to_string().contains("Hook 'exit 3' failed"));
+    }
+}
@@ -0,0 +1,703 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+
+use super::download::normalize_repo_url;
+use super::source::{DEFAULT_REPO, TemplateSource};
+use crate::utils::config::Config;
+
+/// A git hosting service that serves repository archives over HTTP
+///
+/// Each provider knows how to resolve a reference to a commit and where
+/// to download a tarball of that commit.
+pub trait TemplateHost {
+    /// Provider name as accepted by `--provider`
+    fn name(&self) -> &'static str;
+
+    /// Default API base URL for a server at `origin` (`scheme://host[:port]`)
+    fn default_api_base(&self, origin: &str) -> String;
+
+    /// Checks that a repository path is addressable on this provider
+    fn validate_path(&self, path: &[String]) -> Result<()> {
+        if path.len() != 2 {
+            anyhow::bail!(
+                "{} repositories must be given as owner/repo, got '{}'",
+                self.name(),
+                path.join("/")
+            );
+        }
+        Ok(())
+    }
+
+    /// Request that resolves `reference` to a commit
+    fn commit_request(&self, repo: &RemoteRepo, reference: &str) -> ApiRequest;
+
+    /// Extracts the commit id from the response to [`Self::commit_request`]
+    fn parse_commit(&self, body: &str) -> Result<String>;
+
+    /// URL of a gzipped tarball of `commit`
+    fn archive_url(&self, repo: &RemoteRepo, commit: &str) -> String;
+
+    /// Request for one page (starting at 1) of the repository's tags
+    fn tags_request(&self, repo: &RemoteRepo, page: u32) -> ApiRequest;
+
+    /// Extracts tag names from the response to [`Self::tags_request`]
+    fn parse_tags(&self, body: &str) -> Result<Vec<String>> {
+        json_names(&serde_json::from_str(body).context("Invalid JSON response")?)
+    }
+
+    /// Header that authenticates a request with an access token
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", token))
+    }
+}
+
+/// An HTTP GET request against a provider API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiRequest {
+    pub url: String,
+    pub accept: &'static str,
+}
+
+/// A template repository on a remote git host
+pub struct RemoteRepo {
+    /// Provider serving the repository
+    pub host: Box<dyn TemplateHost>,
+    /// Server origin, e.g. `https://gitlab.example.com`
+    pub origin: String,
+    /// Repository path segments (owner/repo, or group/subgroup/repo)
+    pub path: Vec<String>,
+    /// Base URL of the provider API
+    pub api_base: String,
+}
+
+impl RemoteRepo {
+    /// Parses a repository URL
+    ///
+    /// # Arguments
+    ///
+    /// * `repo_url` - Full repository URL, e.g. `https://gitlab.com/group/sub/repo`
+    /// * `provider` - Provider name, required when it can't be told from the host
+    /// * `api_base` - Overrides the provider's default API base URL
+    ///
+    /// # Returns
+    ///
+    /// Returns the parsed repository or an error if the URL is not usable
+    pub fn parse(repo_url: &str, provider: Option<&str>, api_base: Option<&str>) -> Result<Self> {
+        let url = Url::parse(repo_url)
+            .with_context(|| format!("Invalid repository URL: {}", repo_url))?;
+
+        let host_name = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Invalid repository URL: {}", repo_url))?;
+        let origin = url.origin().ascii_serialization();
+
+        let host = match provider {
+            Some(name) => provider_by_name(name)?,
+            None => detect_provider(host_name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Cannot tell which git host '{}' runs. Pass --provider with one of: {}, or use a git+https:// URL to clone it",
+                    host_name,
+                    PROVIDERS.join(", ")
+                )
+            })?,
+        };
+
+        let path: Vec<String> = url
+            .path()
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if path.len() < 2 {
+            anyhow::bail!("Invalid repository URL: {}", repo_url);
+        }
+        host.validate_path(&path)?;
+
+        let api_base = match api_base {
+            Some(base) => base.trim_end_matches('/').to_string(),
+            None => host.default_api_base(&origin),
+        };
+
+        Ok(Self {
+            host,
+            origin,
+            path,
+            api_base,
+        })
+    }
+
+    /// Repository path, e.g. `owner/repo`
+    pub fn full_name(&self) -> String {
+        self.path.join("/")
+    }
+
+    /// Host name of the server, including the port if any
+    pub fn authority(&self) -> &str {
+        self.origin
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(&self.origin)
+    }
+
+    /// Key identifying the repository in the template cache
+    pub fn cache_key(&self) -> String {
+        format!("{}/{}", self.authority(), self.full_name())
+    }
+
+    /// Finds the access token for this repository
+    ///
+    /// Checks `$WENZETU_TOKEN`, then `$GITHUB_TOKEN` for github.com, then
+    /// the credentials for this host in the config file.
+    ///
+    /// Registries and `extends` can point at any host, so `$WENZETU_TOKEN`
+    /// is only sent to the host given with `--repo` (`repo_host`) and the
+    /// host of the default repository. `$GITHUB_TOKEN` is only sent to
+    /// github.com and to a GitHub host given with `--repo`, never to other
+    /// hosts that merely look like GitHub.
+    pub fn token(&self, config: &Config, repo_host: Option<&str>) -> Option<String> {
+        self.token_with(config, repo_host, |name| std::env::var(name).ok())
+    }
+
+    pub(crate) fn token_with<E>(
+        &self,
+        config: &Config,
+        repo_host: Option<&str>,
+        env: E,
+    ) -> Option<String>
+    where
+        E: Fn(&str) -> Option<String>,
+    {
+        let from_env = |name: &str| env(name).filter(|t| !t.trim().is_empty());
+        let default_host = TemplateSource::Remote(normalize_repo_url(DEFAULT_REPO)).host();
+        let trusted = Some(self.authority()) == repo_host
+            || Some(self.authority()) == default_host.as_deref();
+
+        let github = self.host.name() == "github"
+            && (self.authority().eq_ignore_ascii_case("github.com")
+                || Some(self.authority()) == repo_host);
+
+        trusted
+            .then(|| from_env("WENZETU_TOKEN"))
+            .flatten()
+            .or_else(|| github.then(|| from_env("GITHUB_TOKEN")).flatten())
+            .or_else(|| config.token_for(self.authority()).map(str::to_string))
+    }
+
+    /// Header that authenticates requests to this repository
+    pub fn auth_header(&self, token: &str) -> (&'static str, String) {
+        self.host.auth_header(token)
+    }
+
+    /// Request that resolves `reference` to a commit
+    pub fn commit_request(&self, reference: &str) -> ApiRequest {
+        self.host.commit_request(self, reference)
+    }
+
+    /// Extracts a commit id from a provider response
+    pub fn parse_commit(&self, body: &str) -> Result<String> {
+        let commit = self.host.parse_commit(body)?;
+        if commit.is_empty() || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!(
+                "Unexpected commit id '{}' from {}",
+                commit,
+                self.host.name()
+            );
+        }
+        Ok(commit)
+    }
+
+    /// URL of a gzipped tarball of `commit`
+    pub fn archive_url(&self, commit: &str) -> String {
+        self.host.archive_url(self, commit)
+    }
+
+    /// Request for one page (starting at 1) of the repository's tags
+    pub fn tags_request(&self, page: u32) -> ApiRequest {
+        self.host.tags_request(self, page)
+    }
+
+    /// Extracts tag names from a provider response
+    pub fn parse_tags(&self, body: &str) -> Result<Vec<String>> {
+        self.host.parse_tags(body)
+    }
+}
+
+/// Names accepted by `--provider`
+const PROVIDERS: &[&str] = &["github", "gitlab", "gitea", "bitbucket"];
+
+/// Looks up a provider by name
+fn provider_by_name(name: &str) -> Result<Box<dyn TemplateHost>> {
+    match name.to_ascii_lowercase().as_str() {
+        "github" => Ok(Box::new(GitHub)),
+        "gitlab" => Ok(Box::new(GitLab)),
+        "gitea" | "forgejo" => Ok(Box::new(Gitea)),
+        "bitbucket" => Ok(Box::new(Bitbucket)),
+        _ => anyhow::bail!(
+            "Unknown provider '{}'. Expected one of: {}",
+            name,
+            PROVIDERS.join(", ")
+        ),
+    }
+}
+
+/// Guesses the provider from a host name
+fn detect_provider(host: &str) -> Option<Box<dyn TemplateHost>> {
+    let host = host.to_ascii_lowercase();
+    match host.as_str() {
+        "github.com" => Some(Box::new(GitHub)),
+        "gitlab.com" => Some(Box::new(GitLab)),
+        "bitbucket.org" => Some(Box::new(Bitbucket)),
+        "codeberg.org" | "gitea.com" => Some(Box::new(Gitea)),
+        h if h.contains("gitlab") => Some(Box::new(GitLab)),
+        h if h.contains("gitea") || h.contains("forgejo") => Some(Box::new(Gitea)),
+        h if h.contains("github") => Some(Box::new(GitHub)),
+        _ => None,
+    }
+}
+
+/// Percent-encodes a value for use as a single URL path segment or query value
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Reads a string field from a JSON object
+fn json_field(body: &str, field: &str) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(body).context("Invalid JSON response")?;
+    value
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("Missing '{}' in response", field))
+}
+
+/// Reads the `name` of every object in a JSON array
+fn json_names(value: &serde_json::Value) -> Result<Vec<String>> {
+    value
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Expected a list in response"))?
+        .iter()
+        .map(|item| {
+            item.get("name")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("Missing 'name' in response"))
+        })
+        .collect()
+}
+
+/// github.com and GitHub Enterprise
+struct GitHub;
+
+impl TemplateHost for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn default_api_base(&self, origin: &str) -> String {
+        if origin.ends_with("://github.com") {
+            "https://api.github.com".to_string()
+        } else {
+            format!("{}/api/v3", origin)
+        }
+    }
+
+    fn commit_request(&self, repo: &RemoteRepo, reference: &str) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repos/{}/commits/{}",
+                repo.api_base,
+                repo.full_name(),
+                reference
+            ),
+            accept: "application/vnd.github.sha",
+        }
+    }
+
+    fn parse_commit(&self, body: &str) -> Result<String> {
+        Ok(body.trim().to_string())
+    }
+
+    fn archive_url(&self, repo: &RemoteRepo, commit: &str) -> String {
+        format!(
+            "{}/repos/{}/tarball/{}",
+            repo.api_base,
+            repo.full_name(),
+            commit
+        )
+    }
+
+    fn tags_request(&self, repo: &RemoteRepo, page: u32) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repos/{}/tags?per_page=100&page={}",
+                repo.api_base,
+                repo.full_name(),
+                page
+            ),
+            accept: "application/vnd.github+json",
+        }
+    }
+}
+
+/// gitlab.com and self-hosted GitLab, including nested groups
+struct GitLab;
+
+impl TemplateHost for GitLab {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn default_api_base(&self, origin: &str) -> String {
+        format!("{}/api/v4", origin)
+    }
+
+    fn validate_path(&self, _path: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit_request(&self, repo: &RemoteRepo, reference: &str) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/projects/{}/repository/commits/{}",
+                repo.api_base,
+                encode(&repo.full_name()),
+                encode(reference)
+            ),
+            accept: "application/json",
+        }
+    }
+
+    fn parse_commit(&self, body: &str) -> Result<String> {
+        json_field(body, "id")
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("PRIVATE-TOKEN", token.to_string())
+    }
+
+    fn archive_url(&self, repo: &RemoteRepo, commit: &str) -> String {
+        format!(
+            "{}/projects/{}/repository/archive.tar.gz?sha={}",
+            repo.api_base,
+            encode(&repo.full_name()),
+            encode(commit)
+        )
+    }
+
+    fn tags_request(&self, repo: &RemoteRepo, page: u32) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/projects/{}/repository/tags?per_page=100&page={}",
+                repo.api_base,
+                encode(&repo.full_name()),
+                page
+            ),
+            accept: "application/json",
+        }
+    }
+}
+
+/// Gitea, Forgejo and Codeberg
+struct Gitea;
+
+impl TemplateHost for Gitea {
+    fn name(&self) -> &'static str {
+        "gitea"
+    }
+
+    fn default_api_base(&self, origin: &str) -> String {
+        format!("{}/api/v1", origin)
+    }
+
+    fn commit_request(&self, repo: &RemoteRepo, reference: &str) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repos/{}/commits?sha={}&limit=1&stat=false",
+                repo.api_base,
+                repo.full_name(),
+                encode(reference)
+            ),
+            accept: "application/json",
+        }
+    }
+
+    fn parse_commit(&self, body: &str) -> Result<String> {
+        let value: serde_json::Value =
+            serde_json::from_str(body).context("Invalid JSON response")?;
+        value
+            .get(0)
+            .and_then(|c| c.get("sha"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("Missing 'sha' in response"))
+    }
+
+    fn auth_header(&self, token: &str) -> (&'static str, String) {
+        ("Authorization", format!("token {}", token))
+    }
+
+    fn archive_url(&self, repo: &RemoteRepo, commit: &str) -> String {
+        format!(
+            "{}/repos/{}/archive/{}.tar.gz",
+            repo.api_base,
+            repo.full_name(),
+            commit
+        )
+    }
+
+    fn tags_request(&self, repo: &RemoteRepo, page: u32) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repos/{}/tags?limit=50&page={}",
+                repo.api_base,
+                repo.full_name(),
+                page
+            ),
+            accept: "application/json",
+        }
+    }
+}
+
+/// bitbucket.org
+struct Bitbucket;
+
+impl TemplateHost for Bitbucket {
+    fn name(&self) -> &'static str {
+        "bitbucket"
+    }
+
+    fn default_api_base(&self, origin: &str) -> String {
+        if origin.ends_with("://bitbucket.org") {
+            "https://api.bitbucket.org/2.0".to_string()
+        } else {
+            format!("{}/2.0", origin)
+        }
+    }
+
+    fn commit_request(&self, repo: &RemoteRepo, reference: &str) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repositories/{}/commit/{}",
+                repo.api_base,
+                repo.full_name(),
+                encode(reference)
+            ),
+            accept: "application/json",
+        }
+    }
+
+    fn parse_commit(&self, body: &str) -> Result<String> {
+        json_field(body, "hash")
+    }
+
+    fn archive_url(&self, repo: &RemoteRepo, commit: &str) -> String {
+        // Archives are served by the web host, not the API
+        format!("{}/{}/get/{}.tar.gz", repo.origin, repo.full_name(), commit)
+    }
+
+    fn tags_request(&self, repo: &RemoteRepo, page: u32) -> ApiRequest {
+        ApiRequest {
+            url: format!(
+                "{}/repositories/{}/refs/tags?pagelen=100&page={}",
+                repo.api_base,
+                repo.full_name(),
+                page
+            ),
+            accept: "application/json",
+        }
+    }
+
+    fn parse_tags(&self, body: &str) -> Result<Vec<String>> {
+        let value: serde_json::Value =
+            serde_json::from_str(body).context("Invalid JSON response")?;
+        json_names(value.get("values").unwrap_or(&serde_json::Value::Null))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github() {
+        let repo = RemoteRepo::parse("https://github.com/user/repo.git/", None, None).unwrap();
+        assert_eq!(repo.host.name(), "github");
+        assert_eq!(repo.full_name(), "user/repo");
+        assert_eq!(repo.cache_key(), "github.com/user/repo");
+        assert_eq!(
+            repo.commit_request("main"),
+            ApiRequest {
+                url: "https://api.github.com/repos/user/repo/commits/main".to_string(),
+                accept: "application/vnd.github.sha",
+            }
+        );
+        assert_eq!(
+            repo.archive_url("abc123"),
+            "https://api.github.com/repos/user/repo/tarball/abc123"
+        );
+    }
+
+    #[test]
+    fn test_parse_gitlab_nested_groups() {
+        let repo = RemoteRepo::parse("https://gitlab.example.com/group/sub/templates", None, None)
+            .unwrap();
+        assert_eq!(repo.host.name(), "gitlab");
+        assert_eq!(repo.full_name(), "group/sub/templates");
+        assert_eq!(
+            repo.commit_request("feature/x").url,
+            "https://gitlab.example.com/api/v4/projects/group%2Fsub%2Ftemplates/repository/commits/feature%2Fx"
+        );
+        assert_eq!(
+            repo.archive_url("abc123"),
+            "https://gitlab.example.com/api/v4/projects/group%2Fsub%2Ftemplates/repository/archive.tar.gz?sha=abc123"
+        );
+        assert_eq!(repo.parse_commit(r#"{"id": "abc123"}"#).unwrap(), "abc123");
+    }
+
+    #[test]
+    fn test_parse_gitea_and_bitbucket() {
+        let repo = RemoteRepo::parse("https://codeberg.org/user/repo", None, None).unwrap();
+        assert_eq!(
+            repo.archive_url("abc"),
+            "https://codeberg.org/api/v1/repos/user/repo/archive/abc.tar.gz"
+        );
+        assert_eq!(repo.parse_commit(r#"[{"sha": "abc"}]"#).unwrap(), "abc");
+
+        let repo = RemoteRepo::parse("https://bitbucket.org/team/repo", None, None).unwrap();
+        assert_eq!(
+            repo.commit_request("main").url,
+            "https://api.bitbucket.org/2.0/repositories/team/repo/commit/main"
+        );
+        assert_eq!(
+            repo.archive_url("abc"),
+            "https://bitbucket.org/team/repo/get/abc.tar.gz"
+        );
+        assert_eq!(repo.parse_commit(r#"{"hash": "abc"}"#).unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_provider_and_api_base_override() {
+        let repo = RemoteRepo::parse(
+            "http://127.0.0.1:8080/user/repo",
+            Some("github"),
+            Some("http://127.0.0.1:8080/api/"),
+        )
+        .unwrap();
+        assert_eq!(repo.cache_key(), "127.0.0.1:8080/user/repo");
+        assert_eq!(
+            repo.archive_url("abc"),
+            "http://127.0.0.1:8080/api/repos/user/repo/tarball/abc"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(RemoteRepo::parse("https://git.example.com/user/repo", None, None).is_err());
+        assert!(RemoteRepo::parse("https://github.com/user", None, None).is_err());
+        assert!(RemoteRepo::parse("https://github.com/a/b/c", None, None).is_err());
+        assert!(RemoteRepo::parse("https://example.com/a/b", Some("svn"), None).is_err());
+        assert!(RemoteRepo::parse("invalid", None, None).is_err());
+    }
+
+    #[test]
+    fn test_token_lookup() {
+        let config: Config =
+            toml::from_str("[credentials.\"gitlab.example.com\"]\ntoken = \"from-config\"")
+                .unwrap();
+        let github = RemoteRepo::parse("https://github.com/user/repo", None, None).unwrap();
+        let gitlab =
+            RemoteRepo::parse("https://gitlab.example.com/group/repo", None, None).unwrap();
+
+        let env = |name: &str| (name == "GITHUB_TOKEN").then(|| "gh".to_string());
+        assert_eq!(
+            github.token_with(&config, None, env),
+            Some("gh".to_string())
+        );
+        // GITHUB_TOKEN is never sent to other hosts
+        assert_eq!(
+            gitlab.token_with(&config, None, env),
+            Some("from-config".to_string())
+        );
+
+        // WENZETU_TOKEN only goes to the --repo host and the default host
+        let env = |name: &str| (name == "WENZETU_TOKEN").then(|| "wz".to_string());
+        let repo_host = Some("gitlab.example.com");
+        assert_eq!(
+            gitlab.token_with(&config, repo_host, env),
+            Some("wz".to_string())
+        );
+        assert_eq!(
+            github.token_with(&config, repo_host, env),
+            Some("wz".to_string())
+        );
+        let other =
+            RemoteRepo::parse("https://git.evil.example/team/repo", Some("gitea"), None).unwrap();
+        assert_eq!(other.token_with(&config, repo_host, env), None);
+        assert_eq!(
+            gitlab.token_with(&config, None, env),
+            Some("from-config".to_string())
+        );
+
+        // Hosts named like GitHub only get GITHUB_TOKEN when given with --repo
+        let env = |name: &str| (name == "GITHUB_TOKEN").then(|| "gh".to_string());
+        let lookalike = RemoteRepo::parse("https://github.evil.example/x/y", None, None).unwrap();
+        assert_eq!(lookalike.token_with(&config, None, env), None);
+        let forced =
+            RemoteRepo::parse("https://git.evil.example/x/y", Some("github"), None).unwrap();
+        assert_eq!(forced.token_with(&config, None, env), None);
+        assert_eq!(
+            lookalike.token_with(&config, Some("github.evil.example"), env),
+            Some("gh".to_string())
+        );
+
+        assert_eq!(github.token_with(&config, None, |_| None), None);
+        assert_eq!(gitlab.auth_header("t"), ("PRIVATE-TOKEN", "t".to_string()));
+        assert_eq!(
+            github.auth_header("t"),
+            ("Authorization", "Bearer t".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_commit_rejects_garbage() {
+        let repo = RemoteRepo::parse("https://github.com/user/repo", None, None).unwrap();
+        assert!(repo.parse_commit("<html>").is_err());
+        assert!(repo.parse_commit("").is_err());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let github = RemoteRepo::parse("https://github.com/user/repo", None, None).unwrap();
+        assert_eq!(
+            github.tags_request(2).url,
+            "https://api.github.com/repos/user/repo/tags?per_page=100&page=2"
+        );
+        assert_eq!(
+            github
+                .parse_tags(r#"[{"name": "v1.0.0"}, {"name": "v1.1.0"}]"#)
+                .unwrap(),
+            vec!["v1.0.0", "v1.1.0"]
+        );
+
+        let bitbucket = RemoteRepo::parse("https://bitbucket.org/team/repo", None, None).unwrap();
+        assert_eq!(
+            bitbucket
+                .parse_tags(r#"{"values": [{"name": "v2.0.0"}]}"#)
+                .unwrap(),
+            vec!["v2.0.0"]
+        );
+        assert!(bitbucket.parse_tags("<html>").is_err());
+    }
+}
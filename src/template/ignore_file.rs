@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+use super::manifest::MANIFEST_FILE;
+
+/// Name of the ignore file at the root of a template directory
+pub const IGNORE_FILE: &str = ".wenzetuignore";
+
+/// Paths never copied into a project, unless the ignore file re-includes
+/// them with `!`
+const BUILTIN_IGNORES: &[&str] = &[".git/", "node_modules/"];
+
+/// The paths of a template that are left out of projects
+///
+/// Built-in ignores cover the manifest, the ignore file itself, `.git` and
+/// `node_modules`. CI configuration in a template is the project's own, so
+/// it is copied. A `.wenzetuignore` at the root of the template adds
+/// gitignore-style patterns on top, and can re-include built-in paths with
+/// `!`.
+pub struct TemplateIgnore {
+    gitignore: Gitignore,
+}
+
+impl TemplateIgnore {
+    /// Reads the ignore rules of a template directory
+    ///
+    /// # Arguments
+    ///
+    /// * `template_dir` - Path to the template directory
+    ///
+    /// # Returns
+    ///
+    /// Returns the rules, or an error if the ignore file has an invalid
+    /// pattern
+    pub fn load(template_dir: &Path) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(template_dir);
+        for line in BUILTIN_IGNORES {
+            builder.add_line(None, line)?;
+        }
+
+        let path = template_dir.join(IGNORE_FILE);
+        if path.is_file()
+            && let Some(e) = builder.add(&path)
+        {
+            return Err(e).with_context(|| format!("Invalid pattern in {}", IGNORE_FILE));
+        }
+
+        // The manifest and ignore file go last, so they can't be re-included
+        for file in [MANIFEST_FILE, IGNORE_FILE] {
+            builder.add_line(None, &format!("/{}", file))?;
+        }
+
+        Ok(Self {
+            gitignore: builder
+                .build()
+                .with_context(|| format!("Failed to read {}", IGNORE_FILE))?,
+        })
+    }
+
+    /// Returns `true` if a path, or one of its parent directories, is ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_path` - Path relative to the template directory
+    /// * `is_dir` - Whether the path is a directory
+    pub fn is_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        self.gitignore
+            .matched_path_or_any_parents(relative_path, is_dir)
+            .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ignore_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(IGNORE_FILE),
+            "# Kept next to the template\n/tests/\n*.snap\n!keep.snap\n/.gitlab-ci.yml\n!/template.toml\n",
+        )
+        .unwrap();
+        let ignore = TemplateIgnore::load(temp_dir.path()).unwrap();
+        let ignored = |path: &str, is_dir| ignore.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("tests", true));
+        assert!(ignored("tests/fixtures/app.rs", false));
+        assert!(ignored("src/ui/button.snap", false));
+        assert!(!ignored("src/ui/keep.snap", false));
+        assert!(!ignored("src/tests.rs", false));
+
+        assert!(ignored(".gitlab-ci.yml", false));
+        assert!(!ignored("docs/.gitlab-ci.yml", false));
+
+        // Built-in ignores, some of which can be re-included
+        assert!(ignored("node_modules", true));
+        assert!(ignored("web/node_modules/react/index.js", false));
+        assert!(ignored(MANIFEST_FILE, false));
+        assert!(ignored(IGNORE_FILE, false));
+    }
+
+    #[test]
+    fn test_builtin_ignores() {
+        let temp_dir = TempDir::new().unwrap();
+        let ignore = TemplateIgnore::load(temp_dir.path()).unwrap();
+
+        assert!(ignore.is_ignored(Path::new(".git"), true));
+        // CI configuration ships with the project
+        assert!(!ignore.is_ignored(Path::new(".github/workflows/ci.yml"), false));
+        assert!(!ignore.is_ignored(Path::new("src/main.rs"), false));
+        assert!(!ignore.is_ignored(Path::new(".gitignore"), false));
+    }
+}
@@ -0,0 +1,431 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::catalog::is_template_name;
+use super::clone::is_git_url;
+use super::download::DownloadOptions;
+use super::manifest::{ParentTemplate, TemplateManifest};
+use super::provenance::{LayerRecord, Provenance};
+use super::source::{DEFAULT_REF, FetchedTemplate, TemplateSource, fetch_template, local_path};
+use super::verify::Verification;
+use crate::utils::validate_reference;
+
+/// Most templates a chain of `extends` can go through
+const MAX_EXTENDS_DEPTH: usize = 10;
+
+/// A fetched template with its manifest
+pub struct Layer {
+    /// Template directory and where it came from
+    pub fetched: FetchedTemplate,
+    /// The template's `template.toml`
+    pub manifest: TemplateManifest,
+    /// Canonical template directory, telling layers apart
+    dir: PathBuf,
+}
+
+/// The templates a project is generated from, in the order they are rendered
+///
+/// Each template comes after the templates it `extends`, and a template
+/// reached twice (say, two layers extending `default`) is only rendered
+/// once.
+#[derive(Default)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    /// Templates whose parents are being fetched
+    pending: Vec<PathBuf>,
+}
+
+impl LayerStack {
+    /// Fetches a template, and the templates it extends, onto the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Where the template comes from
+    /// * `reference` - Branch, tag or commit SHA for git repositories
+    /// * `template` - Template name
+    /// * `options` - Options for remote downloads
+    ///
+    /// # Returns
+    ///
+    /// Returns the provenance of the template, or an error if it or one of
+    /// its parents can't be fetched, or templates extend each other
+    pub fn push(
+        &mut self,
+        source: &TemplateSource,
+        reference: &str,
+        template: &str,
+        options: &DownloadOptions,
+    ) -> Result<Provenance> {
+        let fetched = fetch_template(source, reference, template, options)?;
+        let manifest = TemplateManifest::load(&fetched.dir)?;
+        manifest.check_version()?;
+        let provenance = fetched.provenance.clone();
+
+        let dir = fs::canonicalize(&fetched.dir)?;
+        if self.pending.contains(&dir) {
+            anyhow::bail!(
+                "Template '{}' extends itself, directly or through its parents",
+                template
+            );
+        }
+        if self.pending.len() >= MAX_EXTENDS_DEPTH {
+            anyhow::bail!(
+                "Template '{}' has an extends chain deeper than {} templates",
+                template,
+                MAX_EXTENDS_DEPTH
+            );
+        }
+        if self.layers.iter().any(|layer| layer.dir == dir) {
+            return Ok(provenance);
+        }
+
+        if let Some(extends) = &manifest.extends {
+            let parent = extends.parent();
+            let (parent_source, parent_ref, parent_options) =
+                parent_source(source, reference, options, &parent)?;
+
+            self.pending.push(dir.clone());
+            let result = self.push(
+                &parent_source,
+                &parent_ref,
+                &parent.template,
+                &parent_options,
+            );
+            self.pending.pop();
+            result?;
+        }
+
+        self.layers.push(Layer {
+            fetched,
+            manifest,
+            dir,
+        });
+        Ok(provenance)
+    }
+
+    /// The templates in the order they are rendered
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Where each template came from, in the order they are rendered
+    pub fn records(&self) -> Vec<LayerRecord> {
+        self.layers
+            .iter()
+            .map(|layer| layer.fetched.provenance.layer())
+            .collect()
+    }
+
+    /// Combines the variables of all templates
+    ///
+    /// A variable declared by several templates keeps the declaration of the
+    /// last one.
+    pub fn variables(&self) -> TemplateManifest {
+        let mut combined = TemplateManifest::default();
+        for layer in &self.layers {
+            combined.add_layer_variables(&layer.manifest);
+        }
+        combined
+    }
+}
+
+/// Determines where a layer comes from
+///
+/// Layers given as a path are used in place. Others come from the
+/// template's repository, or from the directory next to a template given as
+/// a path.
+///
+/// # Arguments
+///
+/// * `template_source` - Where the template the layer goes with comes from
+/// * `layer` - Layer name or path
+pub fn layer_source(template_source: &TemplateSource, layer: &str) -> Result<TemplateSource> {
+    if local_path(layer)?.is_some() {
+        return TemplateSource::parse(None, layer);
+    }
+
+    Ok(match template_source {
+        TemplateSource::LocalTemplate(dir) => {
+            TemplateSource::LocalRepo(dir.parent().unwrap_or(Path::new(".")).to_path_buf())
+        }
+        other => other.clone(),
+    })
+}
+
+/// Determines where the template named by `extends` comes from
+///
+/// Without `repo`, the parent is in the same repository as the child, at the
+/// child's ref unless it names one. A parent in another archive than the
+/// child's is checked against the `sha256` of `extends`, since the checksum
+/// or signature given for the child doesn't cover it.
+///
+/// The manifest comes from the template's author, so its ref is checked
+/// before it reaches git, and git repositories are only cloned from the
+/// host the user gave with `--repo`. Parents on disk are only allowed for
+/// templates that are on disk themselves.
+fn parent_source(
+    child_source: &TemplateSource,
+    child_ref: &str,
+    options: &DownloadOptions,
+    parent: &ParentTemplate,
+) -> Result<(TemplateSource, String, DownloadOptions)> {
+    if let Some(reference) = &parent.reference {
+        validate_reference(reference)?;
+    }
+
+    // Only templates on disk may extend other directories on disk
+    let child_is_local = child_source.host().is_none();
+    if local_path(&parent.template)?.is_some() {
+        if !child_is_local {
+            anyhow::bail!(
+                "The template extends the local path '{}'. Only templates on disk can extend a path.",
+                parent.template
+            );
+        }
+    } else if !is_template_name(&parent.template) {
+        anyhow::bail!("Invalid template name '{}' in extends", parent.template);
+    }
+    if let Some(repo) = &parent.repo
+        && local_path(repo)?.is_some()
+        && !child_is_local
+    {
+        anyhow::bail!(
+            "The template extends '{}' from the local repository '{}'. Only templates on disk can extend a local repository.",
+            parent.template,
+            repo
+        );
+    }
+
+    let mut options = options.clone();
+    let (source, reference) = match &parent.repo {
+        Some(repo) => {
+            let source = TemplateSource::parse(Some(repo), &parent.template)?;
+            if is_git_url(repo) && source.host() != options.repo_host {
+                anyhow::bail!(
+                    "The template extends '{}' from the git repository '{}'. Templates can only be cloned from the host given with --repo.",
+                    parent.template,
+                    repo
+                );
+            }
+
+            // The host options given for the child don't apply to another repository
+            options.provider = None;
+            options.api_url = None;
+            (
+                source,
+                parent
+                    .reference
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_REF.to_string()),
+            )
+        }
+        None => (
+            layer_source(child_source, &parent.template)?,
+            parent
+                .reference
+                .clone()
+                .unwrap_or_else(|| child_ref.to_string()),
+        ),
+    };
+
+    let same_archive = parent.repo.is_none() && reference == child_ref;
+    if !same_archive {
+        if options.verify.is_required() && parent.sha256.is_none() {
+            anyhow::bail!(
+                "The template extends '{}' from another repository or ref, which the checksum or signature doesn't cover. Add its sha256 to [extends] in the template's manifest.",
+                parent.template
+            );
+        }
+        options.verify = Verification {
+            sha256: parent.sha256.clone(),
+            signature: None,
+        };
+    }
+
+    Ok((source, reference, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::manifest::{Extends, MANIFEST_FILE};
+    use tempfile::TempDir;
+
+    fn template(repo: &Path, name: &str, manifest: &str) {
+        fs::create_dir_all(repo.join(name)).unwrap();
+        fs::write(repo.join(name).join(MANIFEST_FILE), manifest).unwrap();
+    }
+
+    fn names(stack: &LayerStack) -> Vec<String> {
+        stack
+            .layers()
+            .iter()
+            .map(|layer| layer.dir.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_extends_chain() {
+        let repo = TempDir::new().unwrap();
+        template(repo.path(), "default", "");
+        template(repo.path(), "company", "extends = \"default\"");
+        template(repo.path(), "team", "extends = \"company\"");
+        template(repo.path(), "tailwind", "extends = \"default\"");
+
+        let source = TemplateSource::LocalRepo(repo.path().to_path_buf());
+        let options = DownloadOptions::default();
+        let mut stack = LayerStack::default();
+        let provenance = stack.push(&source, "main", "team", &options).unwrap();
+        assert_eq!(provenance.template.name, "team");
+        stack.push(&source, "main", "tailwind", &options).unwrap();
+
+        // Parents come first, and `default` is only rendered once
+        assert_eq!(
+            names(&stack),
+            vec!["default", "company", "team", "tailwind"]
+        );
+
+        let records = stack.records();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].name, "default");
+        assert_eq!(records[0].source, repo.path().to_string_lossy());
+
+        // A template given as a path extends the templates next to it
+        let mut stack = LayerStack::default();
+        let path = TemplateSource::LocalTemplate(repo.path().join("company"));
+        stack.push(&path, "main", "company", &options).unwrap();
+        assert_eq!(names(&stack), vec!["default", "company"]);
+    }
+
+    #[test]
+    fn test_extends_cycle() {
+        let repo = TempDir::new().unwrap();
+        template(repo.path(), "a", "extends = \"b\"");
+        template(repo.path(), "b", "extends = \"a\"");
+
+        let source = TemplateSource::LocalRepo(repo.path().to_path_buf());
+        let err = LayerStack::default()
+            .push(&source, "main", "a", &DownloadOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("extends itself"));
+    }
+
+    #[test]
+    fn test_extends_too_deep() {
+        let repo = TempDir::new().unwrap();
+        for i in 0..=MAX_EXTENDS_DEPTH {
+            template(
+                repo.path(),
+                &format!("t{}", i),
+                &format!("extends = \"t{}\"", i + 1),
+            );
+        }
+        template(repo.path(), &format!("t{}", MAX_EXTENDS_DEPTH + 1), "");
+
+        let source = TemplateSource::LocalRepo(repo.path().to_path_buf());
+        let err = LayerStack::default()
+            .push(&source, "main", "t0", &DownloadOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("deeper than 10 templates"));
+    }
+
+    #[test]
+    fn test_parent_verification() {
+        let child = TemplateSource::Remote("https://github.com/acme/templates".to_string());
+        let options = DownloadOptions {
+            verify: Verification {
+                sha256: Some("abc".to_string()),
+                signature: None,
+            },
+            ..Default::default()
+        };
+
+        // The child's archive holds parents at the same ref
+        let parent = Extends::Name("default".to_string()).parent();
+        let (source, reference, parent_options) =
+            parent_source(&child, "v1.0.0", &options, &parent).unwrap();
+        assert_eq!(source, child);
+        assert_eq!(reference, "v1.0.0");
+        assert_eq!(parent_options.verify.sha256.as_deref(), Some("abc"));
+
+        // Other archives need a checksum of their own
+        let mut parent = ParentTemplate {
+            template: "default".to_string(),
+            repo: Some("erickweyunga/uncovr-templates".to_string()),
+            ..Default::default()
+        };
+        assert!(parent_source(&child, "v1.0.0", &options, &parent).is_err());
+        parent.sha256 = Some("def".to_string());
+        let (source, reference, parent_options) =
+            parent_source(&child, "v1.0.0", &options, &parent).unwrap();
+        assert_eq!(
+            source,
+            TemplateSource::Remote("https://github.com/erickweyunga/uncovr-templates".to_string())
+        );
+        assert_eq!(reference, "main");
+        assert_eq!(parent_options.verify.sha256.as_deref(), Some("def"));
+    }
+
+    #[test]
+    fn test_parent_checks() {
+        let child = TemplateSource::Remote("https://github.com/acme/templates".to_string());
+        let options = DownloadOptions::default();
+
+        // Refs that git could read as options are refused
+        let parent = ParentTemplate {
+            template: "default".to_string(),
+            reference: Some("--upload-pack=touch /tmp/pwned".to_string()),
+            ..Default::default()
+        };
+        let err = parent_source(&child, "main", &options, &parent).unwrap_err();
+        assert!(err.to_string().contains("Invalid ref"));
+
+        // Git repositories need --repo on the same host
+        let parent = ParentTemplate {
+            template: "default".to_string(),
+            repo: Some("git@git.example.com:team/templates.git".to_string()),
+            ..Default::default()
+        };
+        let err = parent_source(&child, "main", &options, &parent).unwrap_err();
+        assert!(err.to_string().contains("host given with --repo"));
+
+        let options = DownloadOptions {
+            repo_host: Some("git.example.com".to_string()),
+            ..Default::default()
+        };
+        let (source, _, _) = parent_source(&child, "main", &options, &parent).unwrap();
+        assert_eq!(
+            source,
+            TemplateSource::Git("git@git.example.com:team/templates.git".to_string())
+        );
+
+        // Remote templates can't reach into local directories
+        for (template, repo) in [
+            ("../../x", None),
+            ("/etc", None),
+            ("~/.ssh", None),
+            ("a/b", None),
+            ("default", Some("file:///etc")),
+            ("default", Some("~/templates")),
+            ("default", Some("/srv/templates")),
+        ] {
+            let parent = ParentTemplate {
+                template: template.to_string(),
+                repo: repo.map(str::to_string),
+                ..Default::default()
+            };
+            assert!(parent_source(&child, "main", &options, &parent).is_err());
+        }
+
+        // Templates on disk can
+        let repo = TempDir::new().unwrap();
+        template(repo.path(), "default", "");
+        let local = TemplateSource::LocalRepo(repo.path().to_path_buf());
+        let parent = ParentTemplate {
+            template: repo.path().join("default").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        assert!(parent_source(&local, "main", &options, &parent).is_ok());
+    }
+}
@@ -0,0 +1,755 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use semver::Version;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::filter::PathFilter;
+use super::process::Renderer;
+
+/// Name of the manifest file at the root of a template directory
+pub const MANIFEST_FILE: &str = "template.toml";
+
+/// Variable that is always set to the project name
+pub const PROJECT_NAME_VARIABLE: &str = "project_name";
+
+/// Values of template variables, keyed by variable name
+pub type Values = BTreeMap<String, toml::Value>;
+
+/// A template's `template.toml`
+///
+/// ```toml
+/// description = "Minimal web app"
+/// min_wenzetu_version = "0.2.0"
+/// verbatim = ["static/vendor/**"]
+///
+/// [[variables]]
+/// name = "port"
+/// type = "integer"
+/// description = "Port the dev server listens on"
+/// default = 3000
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateManifest {
+    /// Short description of the template
+    pub description: Option<String>,
+    /// Oldest wenzetu version able to generate the template
+    pub min_wenzetu_version: Option<String>,
+    /// Template this one builds on, generated before it
+    pub extends: Option<Extends>,
+    /// Variables the template uses besides `project_name`
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+    /// Files that are only generated under some condition
+    #[serde(default)]
+    pub files: Vec<FileRule>,
+    /// Paths copied as they are, never rendered
+    #[serde(default)]
+    pub verbatim: Vec<String>,
+    /// Commands run after generation
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+/// The template a template builds on
+///
+/// ```toml
+/// extends = "default"
+/// ```
+///
+/// or, for a template in another repository or ref:
+///
+/// ```toml
+/// [extends]
+/// repo = "erickweyunga/uncovr-templates"
+/// template = "default"
+/// ref = "v1.2.0"
+/// sha256 = "3b4c...e9"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Extends {
+    /// Template in the same repository and ref
+    Name(String),
+    /// Template in another repository or ref
+    Template(ParentTemplate),
+}
+
+/// The table form of `extends`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParentTemplate {
+    /// Template name
+    pub template: String,
+    /// Repository holding the template (defaults to the same repository)
+    pub repo: Option<String>,
+    /// Branch, tag or commit SHA (defaults to the same ref, or main for
+    /// another repository)
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// Expected sha256 of the repository archive
+    pub sha256: Option<String>,
+}
+
+impl Extends {
+    /// Returns the parent template in its table form
+    pub fn parent(&self) -> ParentTemplate {
+        match self {
+            Self::Name(template) => ParentTemplate {
+                template: template.clone(),
+                ..Default::default()
+            },
+            Self::Template(parent) => parent.clone(),
+        }
+    }
+}
+
+/// A variable declared in the manifest
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variable {
+    /// Name used in templates, e.g. `{{ author }}`
+    pub name: String,
+    /// Type of the value
+    #[serde(rename = "type", default)]
+    pub kind: VariableType,
+    /// Shown to the user when asking for a value
+    pub description: Option<String>,
+    /// Value used when none is given. Variables without a default are required.
+    pub default: Option<toml::Value>,
+    /// Regex string values must match
+    pub validation: Option<String>,
+    /// Allowed string values
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+/// Paths included or excluded depending on a condition
+///
+/// ```toml
+/// [[files]]
+/// when = "docker"
+/// include = ["docker/**", "Dockerfile"]
+///
+/// [[files]]
+/// when = "database != 'postgres'"
+/// exclude = ["migrations/**"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRule {
+    /// Expression over template variables, e.g. `docker` or `db == 'pg'`
+    pub when: String,
+    /// Paths generated only when the condition is true
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Paths left out when the condition is true
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// The `[hooks]` table
+///
+/// ```toml
+/// [hooks]
+/// post_generate = ["cargo fetch", "npm install"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Commands run in the project directory once files are generated
+    #[serde(default)]
+    pub post_generate: Vec<String>,
+}
+
+/// Type of a template variable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Bool,
+    Integer,
+}
+
+impl TemplateManifest {
+    /// Loads the manifest of a template directory
+    ///
+    /// # Arguments
+    ///
+    /// * `template_dir` - Path to the template directory
+    ///
+    /// # Returns
+    ///
+    /// Returns an empty manifest if the template has none, or an error if
+    /// the manifest is invalid
+    pub fn load(template_dir: &Path) -> Result<Self> {
+        let path = template_dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", MANIFEST_FILE))?;
+        let manifest: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", MANIFEST_FILE))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Checks variable declarations for mistakes in the template
+    fn validate(&self) -> Result<()> {
+        let name_regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+        let mut seen = HashSet::new();
+
+        for variable in &self.variables {
+            let name = &variable.name;
+            if !name_regex.is_match(name) {
+                anyhow::bail!("Invalid variable name '{}' in {}", name, MANIFEST_FILE);
+            }
+            if name == PROJECT_NAME_VARIABLE {
+                anyhow::bail!(
+                    "'{}' is set by wenzetu and can't be declared in {}",
+                    name,
+                    MANIFEST_FILE
+                );
+            }
+            if !seen.insert(name) {
+                anyhow::bail!("Variable '{}' is declared twice in {}", name, MANIFEST_FILE);
+            }
+            if let Some(pattern) = &variable.validation {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid validation regex for variable '{}'", name))?;
+            }
+            if !variable.choices.is_empty() && variable.kind != VariableType::String {
+                anyhow::bail!("Variable '{}' has choices but is not a string", name);
+            }
+            if let Some(default) = &variable.default {
+                variable
+                    .check(default)
+                    .with_context(|| format!("Invalid default in {}", MANIFEST_FILE))?;
+            }
+        }
+
+        if let Some(extends) = &self.extends
+            && extends.parent().template.trim().is_empty()
+        {
+            anyhow::bail!("Missing template name for extends in {}", MANIFEST_FILE);
+        }
+
+        for rule in &self.files {
+            if rule.when.trim().is_empty() {
+                anyhow::bail!("Missing condition for [[files]] in {}", MANIFEST_FILE);
+            }
+            PathFilter::new(&rule.include)?;
+            PathFilter::new(&rule.exclude)?;
+        }
+        PathFilter::new(&self.verbatim)?;
+
+        Ok(())
+    }
+
+    /// Adds the variables of a layer applied on top of this template
+    ///
+    /// A variable declared by both keeps the layer's declaration.
+    pub fn add_layer_variables(&mut self, layer: &TemplateManifest) {
+        for variable in &layer.variables {
+            match self.variables.iter_mut().find(|v| v.name == variable.name) {
+                Some(existing) => *existing = variable.clone(),
+                None => self.variables.push(variable.clone()),
+            }
+        }
+    }
+
+    /// Builds the filter of paths left out of the project
+    ///
+    /// The manifest itself is always left out, along with the paths of
+    /// `[[files]]` rules whose condition doesn't allow them. `verbatim`
+    /// paths are marked to be copied without rendering.
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - Renderer holding the variable values
+    pub fn path_filter(&self, renderer: &Renderer) -> Result<PathFilter> {
+        let mut excluded = vec![MANIFEST_FILE.to_string()];
+
+        for rule in &self.files {
+            let enabled = renderer.eval_condition(&rule.when).with_context(|| {
+                format!("Invalid condition '{}' in {}", rule.when, MANIFEST_FILE)
+            })?;
+            let patterns = if enabled {
+                &rule.exclude
+            } else {
+                &rule.include
+            };
+            excluded.extend(patterns.iter().cloned());
+        }
+
+        PathFilter::new(&excluded)?.with_verbatim(&self.verbatim)
+    }
+
+    /// Renders the post-generation hook commands
+    ///
+    /// Commands can use template variables, e.g. `cargo run --bin {{ project_name }}`.
+    pub fn hook_commands(&self, renderer: &Renderer) -> Result<Vec<String>> {
+        self.hooks
+            .post_generate
+            .iter()
+            .map(|command| renderer.render_str(MANIFEST_FILE, command))
+            .collect()
+    }
+
+    /// Fails if the running wenzetu is older than the template requires
+    pub fn check_version(&self) -> Result<()> {
+        let Some(required) = &self.min_wenzetu_version else {
+            return Ok(());
+        };
+
+        let required = Version::parse(required).with_context(|| {
+            format!(
+                "Invalid min_wenzetu_version '{}' in {}",
+                required, MANIFEST_FILE
+            )
+        })?;
+        let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        if current < required {
+            anyhow::bail!(
+                "This template requires wenzetu {} or newer (you have {}). Update wenzetu and try again.",
+                required,
+                current
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Collects the values of all variables
+    ///
+    /// `project_name` is always set. Other variables take the value given
+    /// in `provided`, then the answer from `prompt`, then their default.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_name` - Name of the project being created
+    /// * `provided` - Values given with `--var` or `--vars-file`
+    /// * `prompt` - Asks for a value, returning `None` when not interactive
+    ///
+    /// # Returns
+    ///
+    /// Returns the values, or an error listing required variables that have
+    /// no value
+    pub fn resolve_values<F>(
+        &self,
+        project_name: &str,
+        provided: &Values,
+        mut prompt: F,
+    ) -> Result<Values>
+    where
+        F: FnMut(&Variable) -> Result<Option<toml::Value>>,
+    {
+        if let Some(name) = provided
+            .keys()
+            .find(|name| !self.variables.iter().any(|v| &v.name == *name))
+        {
+            if name == PROJECT_NAME_VARIABLE {
+                anyhow::bail!("'{}' is set from the application name", name);
+            }
+            anyhow::bail!("Template has no variable named '{}'", name);
+        }
+
+        let mut values = Values::new();
+        values.insert(
+            PROJECT_NAME_VARIABLE.to_string(),
+            toml::Value::String(project_name.to_string()),
+        );
+
+        let mut missing = Vec::new();
+        for variable in &self.variables {
+            let value = match provided.get(&variable.name) {
+                Some(value) => Some(variable.coerce(value.clone())?),
+                None => match prompt(variable)? {
+                    Some(value) => Some(value),
+                    None => variable.default.clone(),
+                },
+            };
+
+            match value {
+                Some(value) => {
+                    values.insert(variable.name.clone(), value);
+                }
+                None => missing.push(variable.name.as_str()),
+            }
+        }
+
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Missing values for template variables: {}. Pass them with --var name=value or --vars-file.",
+                missing.join(", ")
+            );
+        }
+
+        Ok(values)
+    }
+}
+
+impl Variable {
+    /// Converts a value given as text to the variable's type and checks it
+    ///
+    /// Values from `--var` are always strings, so `true`/`false`/`yes`/`no`
+    /// are accepted for bools and numbers for integers.
+    pub fn coerce(&self, value: toml::Value) -> Result<toml::Value> {
+        let value = match (self.kind, value) {
+            (VariableType::Bool, toml::Value::String(s)) => {
+                match s.trim().to_ascii_lowercase().as_str() {
+                    "true" | "yes" | "y" | "1" => toml::Value::Boolean(true),
+                    "false" | "no" | "n" | "0" => toml::Value::Boolean(false),
+                    _ => anyhow::bail!("Variable '{}' expects a bool, got '{}'", self.name, s),
+                }
+            }
+            (VariableType::Integer, toml::Value::String(s)) => match s.trim().parse() {
+                Ok(n) => toml::Value::Integer(n),
+                Err(_) => anyhow::bail!("Variable '{}' expects an integer, got '{}'", self.name, s),
+            },
+            (_, value) => value,
+        };
+
+        self.check(&value)?;
+        Ok(value)
+    }
+
+    /// Checks a value against the variable's type, choices and validation regex
+    pub fn check(&self, value: &toml::Value) -> Result<()> {
+        match (self.kind, value) {
+            (VariableType::String, toml::Value::String(s)) => {
+                if !self.choices.is_empty() && !self.choices.contains(s) {
+                    anyhow::bail!(
+                        "'{}' is not a valid value for '{}'. Expected one of: {}",
+                        s,
+                        self.name,
+                        self.choices.join(", ")
+                    );
+                }
+                if let Some(pattern) = &self.validation
+                    && !Regex::new(pattern)?.is_match(s)
+                {
+                    anyhow::bail!(
+                        "'{}' is not a valid value for '{}'. It must match {}",
+                        s,
+                        self.name,
+                        pattern
+                    );
+                }
+                Ok(())
+            }
+            (VariableType::Bool, toml::Value::Boolean(_)) => Ok(()),
+            (VariableType::Integer, toml::Value::Integer(_)) => Ok(()),
+            (kind, value) => anyhow::bail!(
+                "Variable '{}' expects a {}, got {}",
+                self.name,
+                kind.as_str(),
+                value
+            ),
+        }
+    }
+}
+
+impl VariableType {
+    /// Name of the type as written in the manifest
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Bool => "bool",
+            Self::Integer => "integer",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn no_prompt(_: &Variable) -> Result<Option<toml::Value>> {
+        Ok(None)
+    }
+
+    fn load(content: &str) -> Result<TemplateManifest> {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(MANIFEST_FILE), content).unwrap();
+        TemplateManifest::load(temp_dir.path())
+    }
+
+    #[test]
+    fn test_missing_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = TemplateManifest::load(temp_dir.path()).unwrap();
+        assert_eq!(manifest, TemplateManifest::default());
+
+        let values = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap();
+        assert_eq!(values["project_name"].as_str(), Some("my-app"));
+    }
+
+    #[test]
+    fn test_resolve_defaults() {
+        let manifest = load(
+            r#"
+description = "Web app"
+
+[[variables]]
+name = "port"
+type = "integer"
+default = 3000
+
+[[variables]]
+name = "database"
+choices = ["postgres", "sqlite"]
+default = "sqlite"
+
+[[variables]]
+name = "docker"
+type = "bool"
+default = false
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.description.as_deref(), Some("Web app"));
+        let values = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap();
+        assert_eq!(values["port"].as_integer(), Some(3000));
+        assert_eq!(values["database"].as_str(), Some("sqlite"));
+        assert_eq!(values["docker"].as_bool(), Some(false));
+        assert_eq!(values["project_name"].as_str(), Some("my-app"));
+    }
+
+    #[test]
+    fn test_add_layer_variables() {
+        let mut manifest =
+            load("[[variables]]\nname = \"port\"\ntype = \"integer\"\ndefault = 3000\n").unwrap();
+        let layer = load(
+            "[[variables]]\nname = \"port\"\ntype = \"integer\"\ndefault = 8080\n\n[[variables]]\nname = \"database_url\"\ndefault = \"postgres://localhost\"\n",
+        )
+        .unwrap();
+        manifest.add_layer_variables(&layer);
+
+        let values = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap();
+        assert_eq!(values["port"].as_integer(), Some(8080));
+        assert_eq!(
+            values["database_url"].as_str(),
+            Some("postgres://localhost")
+        );
+    }
+
+    #[test]
+    fn test_missing_required_variables() {
+        let manifest = load(
+            r#"
+[[variables]]
+name = "author"
+
+[[variables]]
+name = "license"
+"#,
+        )
+        .unwrap();
+
+        let err = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("author, license"));
+
+        // Provided values and prompt answers fill them in
+        let mut provided = Values::new();
+        provided.insert("author".to_string(), toml::Value::from("Ada"));
+        let values = manifest
+            .resolve_values("my-app", &provided, |variable| {
+                Ok(Some(toml::Value::from(format!("{}?", variable.name))))
+            })
+            .unwrap();
+        assert_eq!(values["author"].as_str(), Some("Ada"));
+        assert_eq!(values["license"].as_str(), Some("license?"));
+
+        provided.insert("typo".to_string(), toml::Value::from("x"));
+        assert!(
+            manifest
+                .resolve_values("my-app", &provided, no_prompt)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_coerce_text_values() {
+        let manifest = load(
+            r#"
+[[variables]]
+name = "docker"
+type = "bool"
+
+[[variables]]
+name = "port"
+type = "integer"
+"#,
+        )
+        .unwrap();
+        let docker = &manifest.variables[0];
+        let port = &manifest.variables[1];
+
+        assert_eq!(
+            docker.coerce(toml::Value::from("yes")).unwrap(),
+            toml::Value::Boolean(true)
+        );
+        assert_eq!(
+            port.coerce(toml::Value::from("8080")).unwrap(),
+            toml::Value::Integer(8080)
+        );
+        assert_eq!(
+            port.coerce(toml::Value::Integer(1)).unwrap(),
+            toml::Value::Integer(1)
+        );
+        assert!(docker.coerce(toml::Value::from("maybe")).is_err());
+        assert!(port.coerce(toml::Value::from("eighty")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        assert!(load("[[variables]]\nname = \"project_name\"").is_err());
+        assert!(load("[[variables]]\nname = \"bad name\"").is_err());
+        assert!(load("[[variables]]\nname = \"a\"\nvalidation = \"(\"").is_err());
+        assert!(load("[[variables]]\nname = \"a\"\ntype = \"bool\"\ndefault = \"yes\"").is_err());
+        assert!(load("[[variables]]\nname = \"a\"\nchoices = [\"x\"]\ndefault = \"y\"").is_err());
+        assert!(load("[[variables]]\nname = \"a\"\n[[variables]]\nname = \"a\"").is_err());
+        assert!(load("unknown = 1").is_err());
+        assert!(load("extends = \"\"").is_err());
+        assert!(load("[extends]\nrepo = \"acme/templates\"").is_err());
+    }
+
+    #[test]
+    fn test_extends() {
+        let manifest = load("extends = \"default\"").unwrap();
+        assert_eq!(manifest.extends.unwrap().parent().template, "default");
+
+        let manifest = load(
+            "[extends]\nrepo = \"erickweyunga/uncovr-templates\"\ntemplate = \"default\"\nref = \"v1.2.0\"\n",
+        )
+        .unwrap();
+        let parent = manifest.extends.unwrap().parent();
+        assert_eq!(parent.template, "default");
+        assert_eq!(
+            parent.repo.as_deref(),
+            Some("erickweyunga/uncovr-templates")
+        );
+        assert_eq!(parent.reference.as_deref(), Some("v1.2.0"));
+        assert_eq!(parent.sha256, None);
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let manifest = load(
+            r#"
+verbatim = ["static/**"]
+
+[[variables]]
+name = "docker"
+type = "bool"
+default = false
+
+[[variables]]
+name = "database"
+default = "sqlite"
+
+[[files]]
+when = "docker"
+include = ["docker/**", "Dockerfile"]
+
+[[files]]
+when = "database != 'postgres'"
+exclude = ["migrations/**"]
+"#,
+        )
+        .unwrap();
+
+        let values = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap();
+        let filter = manifest.path_filter(&Renderer::new(&values)).unwrap();
+
+        assert!(filter.is_excluded(Path::new(MANIFEST_FILE)));
+        assert!(filter.is_excluded(Path::new("docker/compose.yml")));
+        assert!(filter.is_excluded(Path::new("Dockerfile")));
+        assert!(filter.is_excluded(Path::new("migrations/001.sql")));
+        assert!(!filter.is_excluded(Path::new("src/main.rs")));
+        assert!(filter.is_verbatim(Path::new("static/app.min.js")));
+        assert!(!filter.is_verbatim(Path::new("src/main.rs")));
+
+        let mut provided = Values::new();
+        provided.insert("docker".to_string(), toml::Value::from(true));
+        provided.insert("database".to_string(), toml::Value::from("postgres"));
+        let values = manifest
+            .resolve_values("my-app", &provided, no_prompt)
+            .unwrap();
+        let filter = manifest.path_filter(&Renderer::new(&values)).unwrap();
+
+        assert!(!filter.is_excluded(Path::new("docker/compose.yml")));
+        assert!(!filter.is_excluded(Path::new("migrations/001.sql")));
+
+        assert!(load("[[files]]\nwhen = \"\"\ninclude = [\"a\"]").is_err());
+        assert!(load("[[files]]\nwhen = \"a\"\ninclude = [\"a[\"]").is_err());
+        assert!(load("verbatim = [\"a[\"]").is_err());
+    }
+
+    #[test]
+    fn test_hook_commands() {
+        let manifest =
+            load("[hooks]\npost_generate = [\"cargo fetch\", \"echo {{ project_name }}\"]")
+                .unwrap();
+        let values = manifest
+            .resolve_values("my-app", &Values::new(), no_prompt)
+            .unwrap();
+
+        assert_eq!(
+            manifest.hook_commands(&Renderer::new(&values)).unwrap(),
+            vec!["cargo fetch", "echo my-app"]
+        );
+    }
+
+    #[test]
+    fn test_check_validation_regex() {
+        let variable = Variable {
+            name: "crate_name".to_string(),
+            kind: VariableType::String,
+            description: None,
+            default: None,
+            validation: Some("^[a-z_]+$".to_string()),
+            choices: Vec::new(),
+        };
+
+        assert!(variable.check(&toml::Value::from("my_crate")).is_ok());
+        assert!(variable.check(&toml::Value::from("My-Crate")).is_err());
+        assert!(variable.check(&toml::Value::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_check_version() {
+        let mut manifest = TemplateManifest {
+            min_wenzetu_version: Some("0.0.1".to_string()),
+            ..Default::default()
+        };
+        assert!(manifest.check_version().is_ok());
+
+        manifest.min_wenzetu_version = Some("999.0.0".to_string());
+        let err = manifest.check_version().unwrap_err().to_string();
+        assert!(err.contains("requires wenzetu 999.0.0"));
+
+        manifest.min_wenzetu_version = Some("soon".to_string());
+        assert!(manifest.check_version().is_err());
+    }
+}
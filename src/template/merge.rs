@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table};
+
+/// Combines a layer's file with the one already in the project
+///
+/// Layers add to what the templates before them generated:
+///
+/// - `Cargo.toml` tables are merged key by key, so the dependencies,
+///   features and `[package.metadata.tailwind]` settings of both are kept.
+///   Arrays such as `features` or `tw-input` keep the items of both, and
+///   the layer wins for any other value.
+/// - `.gitignore` keeps its lines and gets the layer's new ones appended.
+/// - Any other file is replaced by the layer's.
+///
+/// # Arguments
+///
+/// * `target` - Path of the file in the project
+/// * `existing` - Content already in the project
+/// * `layer` - Rendered content from the layer
+///
+/// # Returns
+///
+/// Returns the merged content, or an error if a `Cargo.toml` isn't valid TOML
+pub fn merge_file(target: &Path, existing: &str, layer: &str) -> Result<String> {
+    match target.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => merge_toml(existing, layer)
+            .with_context(|| format!("Failed to merge '{}'", target.display())),
+        Some(".gitignore") => Ok(merge_lines(existing, layer)),
+        _ => Ok(layer.to_string()),
+    }
+}
+
+/// Merges the tables of two TOML documents, keeping the formatting of the
+/// existing one
+fn merge_toml(existing: &str, layer: &str) -> Result<String> {
+    let mut document: DocumentMut = existing.parse()?;
+    let layer: DocumentMut = layer.parse()?;
+
+    let mut next_position = last_position(document.as_table()) + 1;
+    for (key, item) in layer.iter() {
+        match document.get_mut(key) {
+            Some(current) => merge_item(current, item, &mut next_position),
+            None => {
+                document.insert(key, placed(item, &mut next_position));
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Merges a layer's value into an existing one
+///
+/// Tables the layer adds go after the existing ones, numbered from
+/// `next_position`.
+fn merge_item(current: &mut Item, layer: &Item, next_position: &mut isize) {
+    if let Some(table) = current.as_table_like_mut()
+        && let Some(layer_table) = layer.as_table_like()
+    {
+        for (key, item) in layer_table.iter() {
+            match table.get_mut(key) {
+                Some(current) => merge_item(current, item, next_position),
+                None => {
+                    table.insert(key, placed(item, next_position));
+                }
+            }
+        }
+        return;
+    }
+
+    if let Some(array) = current.as_array_mut()
+        && let Some(layer_array) = layer.as_array()
+    {
+        for value in layer_array {
+            if !array.iter().any(|other| same_text(other, value)) {
+                let mut value = value.clone();
+                value.decor_mut().clear();
+                array.push(value);
+            }
+        }
+        return;
+    }
+
+    if let Some(tables) = current.as_array_of_tables_mut()
+        && let Some(layer_tables) = layer.as_array_of_tables()
+    {
+        for table in layer_tables {
+            if !tables.iter().any(|other| same_text(other, table)) {
+                let mut table = table.clone();
+                place_table(&mut table, next_position);
+                tables.push(table);
+            }
+        }
+        return;
+    }
+
+    *current = layer.clone();
+}
+
+/// Copies an item from a layer, moving its tables to the end of the document
+fn placed(item: &Item, next_position: &mut isize) -> Item {
+    let mut item = item.clone();
+    match &mut item {
+        Item::Table(table) => place_table(table, next_position),
+        Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                place_table(table, next_position);
+            }
+        }
+        _ => {}
+    }
+    item
+}
+
+/// Numbers a table and its subtables from `next_position`, each after a
+/// blank line
+fn place_table(table: &mut Table, next_position: &mut isize) {
+    if !table.is_implicit() {
+        table.set_position(Some(*next_position));
+        table.decor_mut().set_prefix("\n");
+        *next_position += 1;
+    }
+    for (_, item) in table.iter_mut() {
+        if let Some(child) = item.as_table_mut() {
+            place_table(child, next_position);
+        } else if let Some(tables) = item.as_array_of_tables_mut() {
+            for child in tables.iter_mut() {
+                place_table(child, next_position);
+            }
+        }
+    }
+}
+
+/// Highest position of a table in a document
+fn last_position(table: &Table) -> isize {
+    table
+        .iter()
+        .flat_map(|(_, item)| match item {
+            Item::Table(child) => vec![child.position().unwrap_or(0), last_position(child)],
+            Item::ArrayOfTables(tables) => tables
+                .iter()
+                .map(|child| child.position().unwrap_or(0).max(last_position(child)))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Compares two TOML values, ignoring whitespace and comments around them
+fn same_text<T: std::fmt::Display>(a: &T, b: &T) -> bool {
+    a.to_string().trim() == b.to_string().trim()
+}
+
+/// Appends the lines of a layer's file that the existing file lacks
+fn merge_lines(existing: &str, layer: &str) -> String {
+    let mut seen: HashSet<&str> = existing.lines().map(str::trim).collect();
+    let added: Vec<&str> = layer
+        .lines()
+        .filter(|line| !line.trim().is_empty() && seen.insert(line.trim()))
+        .collect();
+    if added.is_empty() {
+        return existing.to_string();
+    }
+
+    let mut merged = existing.to_string();
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    for line in added {
+        merged.push_str(line);
+        merged.push('\n');
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_cargo_toml() {
+        let existing = r#"[package]
+name = "my-app"
+version = "0.1.0"
+
+[package.metadata.tailwind]
+tw-input = ["style/main.css"]
+tw-output = "public/main.css"
+
+[dependencies]
+# Web framework
+axum = "0.8"
+serde = "1"
+"#;
+        let layer = r#"[package.metadata.tailwind]
+tw-input = ["style/main.css", "style/forms.css"]
+tw-watch-enabled = true
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+sqlx = { version = "0.8", features = ["postgres"] }
+
+[features]
+db = ["dep:sqlx"]
+"#;
+
+        let merged = merge_file(Path::new("Cargo.toml"), existing, layer).unwrap();
+        assert_eq!(
+            merged,
+            r#"[package]
+name = "my-app"
+version = "0.1.0"
+
+[package.metadata.tailwind]
+tw-input = ["style/main.css", "style/forms.css"]
+tw-output = "public/main.css"
+tw-watch-enabled = true
+
+[dependencies]
+# Web framework
+axum = "0.8"
+serde = { version = "1", features = ["derive"] }
+sqlx = { version = "0.8", features = ["postgres"] }
+
+[features]
+db = ["dep:sqlx"]
+"#
+        );
+
+        // Inline dependency tables are merged too
+        let merged = merge_file(
+            Path::new("Cargo.toml"),
+            "[dependencies]\ntokio = { version = \"1\", features = [\"rt\"] }\n",
+            "[dependencies]\ntokio = { version = \"1\", features = [\"macros\"] }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            merged,
+            "[dependencies]\ntokio = { version = \"1\", features = [\"rt\", \"macros\"] }\n"
+        );
+
+        // Tables the layer adds go after the existing ones
+        let merged = merge_file(
+            Path::new("Cargo.toml"),
+            "[package]\nname = \"my-app\"\n\n[dependencies]\naxum = \"0.8\"\n",
+            "[dev-dependencies]\ninsta = \"1\"\n\n[dependencies]\nsqlx = \"0.8\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            merged,
+            "[package]\nname = \"my-app\"\n\n[dependencies]\naxum = \"0.8\"\nsqlx = \"0.8\"\n\n[dev-dependencies]\ninsta = \"1\"\n"
+        );
+
+        assert!(merge_file(Path::new("Cargo.toml"), "[package", "").is_err());
+    }
+
+    #[test]
+    fn test_merge_gitignore_and_other_files() {
+        let merged = merge_file(
+            Path::new(".gitignore"),
+            "/target\n.env",
+            "/target\n\n# Tailwind\n/public/main.css\n",
+        )
+        .unwrap();
+        assert_eq!(merged, "/target\n.env\n# Tailwind\n/public/main.css\n");
+
+        let merged = merge_file(Path::new("src/main.rs"), "fn main() {}\n", "// layer\n").unwrap();
+        assert_eq!(merged, "// layer\n");
+    }
+}
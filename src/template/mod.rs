@@ -1,6 +1,29 @@
+pub mod archive;
+pub mod cache;
+pub mod catalog;
+pub mod clone;
 pub mod download;
+pub mod filter;
+pub mod hooks;
+pub mod host;
+pub mod ignore_file;
+pub mod layers;
+pub mod manifest;
+pub mod merge;
 pub mod process;
+pub mod provenance;
+pub mod registry;
+pub mod source;
+pub mod variables;
+pub mod verify;
+pub mod version;
 
 // Re-export commonly used functions
-pub use download::{download_template, normalize_repo_url};
-pub use process::replace_placeholders;
+pub use cache::TemplateCache;
+pub use download::DownloadOptions;
+pub use hooks::HookPolicy;
+pub use layers::LayerStack;
+pub use process::Renderer;
+pub use registry::Registry;
+pub use source::TemplateSource;
+pub use verify::Verification;
@@ -1,234 +1,604 @@
-use anyhow::Result;
-use regex::Regex;
+use anyhow::{Context, Result};
+use heck::{ToKebabCase, ToSnakeCase, ToUpperCamelCase};
+use minijinja::{Environment, UndefinedBehavior};
+use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::utils::should_skip_path;
+use super::filter::PathFilter;
+use super::ignore_file::TemplateIgnore;
+use super::manifest::Values;
+use super::merge::merge_file;
+use crate::utils::file_ops::{copy_symlink, resolve_inside};
+use crate::utils::{is_binary_file, should_skip_path};
 
-/// Replaces placeholders in template files with actual values
+/// Renders template files with the values of template variables
 ///
-/// # Arguments
-///
-/// * `project_path` - Path to the project directory
-/// * `project_name` - Name of the project to replace placeholders with
-///
-/// # Returns
-///
-/// Returns `Ok(())` if successful, or an error if file operations fail
-///
-/// # Examples
-///
-/// This function will replace all occurrences of `{{project_name}}` in text files
-/// with the actual project name.
-pub fn replace_placeholders(project_path: &Path, project_name: &str) -> Result<()> {
-    let placeholder_regex = Regex::new(r"\{\{project_name\}\}").unwrap();
-
-    for entry in WalkDir::new(project_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-
-        // Skip binary files and target directory
-        if should_skip_path(path) {
-            continue;
-        }
-
-        // Try to read file as text
-        if let Ok(content) = fs::read_to_string(path) {
-            // Replace placeholders
-            let new_content = placeholder_regex.replace_all(&content, project_name);
+/// Templates use Jinja syntax: `{{ name }}` for values, `{% if %}` and
+/// `{% for %}` blocks, and the `snake_case`, `pascal_case` and `kebab_case`
+/// filters on top of the built-in ones. Literal braces are written as
+/// `{{ "{{" }}`, or by wrapping a whole section in `{% raw %}...{% endraw %}`.
+pub struct Renderer {
+    env: Environment<'static>,
+    context: minijinja::Value,
+}
 
-            // Write back if changes were made
-            if content != new_content.as_ref() {
-                let mut file = fs::File::create(path)?;
-                file.write_all(new_content.as_bytes())?;
-            }
+impl Renderer {
+    /// Creates a renderer for a set of variable values
+    pub fn new(values: &Values) -> Self {
+        let mut env = Environment::new();
+        // Using a variable the template doesn't define is an error
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        // Block tags on their own line don't leave blank lines behind
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+
+        env.add_filter("snake_case", |value: String| value.to_snake_case());
+        env.add_filter("pascal_case", |value: String| value.to_upper_camel_case());
+        env.add_filter("kebab_case", |value: String| value.to_kebab_case());
+
+        Self {
+            env,
+            context: minijinja::Value::from_serialize(values),
         }
     }
 
-    Ok(())
-}
+    /// Renders a single template string
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name shown in error messages, usually the file path
+    /// * `source` - Template source
+    pub fn render_str(&self, name: &str, source: &str) -> Result<String> {
+        self.env
+            .render_named_str(name, source, &self.context)
+            .map_err(|e| anyhow::anyhow!(render_error(&e)))
+    }
 
-/// Replaces multiple placeholders in template files
-///
-/// # Arguments
-///
-/// * `project_path` - Path to the project directory
-/// * `replacements` - Map of placeholder names to their replacement values
-///
-/// # Returns
-///
-/// Returns `Ok(())` if successful, or an error if file operations fail
-///
-/// # Examples
-///
-/// ```no_run
-/// use std::collections::HashMap;
-/// use std::path::Path;
-///
-/// let mut replacements = HashMap::new();
-/// replacements.insert("project_name".to_string(), "my-app".to_string());
-/// replacements.insert("author".to_string(), "John Doe".to_string());
-///
-/// replace_multiple_placeholders(Path::new("./project"), &replacements).unwrap();
-/// ```
-#[allow(dead_code)]
-pub fn replace_multiple_placeholders(
-    project_path: &Path,
-    replacements: &std::collections::HashMap<String, String>,
-) -> Result<()> {
-    for entry in WalkDir::new(project_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-
-        // Skip binary files and target directory
-        if should_skip_path(path) {
-            continue;
+    /// Evaluates a condition such as `docker` or `database == 'postgres'`
+    pub fn eval_condition(&self, expression: &str) -> Result<bool> {
+        let value = self
+            .env
+            .compile_expression(expression)
+            .and_then(|expr| expr.eval(&self.context))
+            .map_err(|e| anyhow::anyhow!(render_error(&e)))?;
+        if value.is_undefined() {
+            anyhow::bail!("'{}' uses a variable that isn't defined", expression);
         }
+        Ok(value.is_true())
+    }
 
-        // Try to read file as text
-        if let Ok(mut content) = fs::read_to_string(path) {
-            let original_content = content.clone();
+    /// Renders a template directory into a project directory
+    ///
+    /// File and directory names are rendered like file contents, so a
+    /// template can ship `src/{{ project_name }}_core/mod.rs`. A name that
+    /// renders to nothing skips that path, and everything under it. Binary
+    /// files, `verbatim` paths of the filter and anything under `target/`
+    /// are copied as they are. Files keep their permission bits, and
+    /// relative symlinks that stay inside the project are recreated. Paths
+    /// ignored by the template's `.wenzetuignore` and the built-in ignores
+    /// are left out, see [`TemplateIgnore`].
+    ///
+    /// Rendering a layer into a project that already has files merges
+    /// text files into the existing ones, see [`merge_file`].
+    ///
+    /// # Arguments
+    ///
+    /// * `template_dir` - Path to the template directory
+    /// * `project_path` - Path to the project directory
+    /// * `filter` - Paths to leave out or copy without rendering
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if successful, or an error naming the file that
+    /// failed to render, or the paths that render to the same name
+    pub fn render_dir(
+        &self,
+        template_dir: &Path,
+        project_path: &Path,
+        filter: &PathFilter,
+    ) -> Result<()> {
+        let ignore = TemplateIgnore::load(template_dir)?;
+        fs::create_dir_all(project_path)?;
+        let root = fs::canonicalize(project_path)?;
+        let mut rendered_paths: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        let mut entries = WalkDir::new(template_dir)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter();
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let path = entry.path();
+            let relative_path = path.strip_prefix(template_dir)?;
+
+            if filter.is_excluded(relative_path)
+                || ignore.is_ignored(relative_path, entry.file_type().is_dir())
+            {
+                if entry.file_type().is_dir() {
+                    entries.skip_current_dir();
+                }
+                continue;
+            }
 
-            // Replace each placeholder
-            for (key, value) in replacements {
-                let placeholder = format!("{{{{{}}}}}", key);
-                content = content.replace(&placeholder, value);
+            let Some(target) = self.render_path(relative_path)? else {
+                if entry.file_type().is_dir() {
+                    entries.skip_current_dir();
+                }
+                continue;
+            };
+
+            if let Some(other) = rendered_paths.insert(target.clone(), relative_path.to_path_buf())
+                && !entry.file_type().is_dir()
+            {
+                anyhow::bail!(
+                    "Both '{}' and '{}' render to '{}'",
+                    other.display(),
+                    relative_path.display(),
+                    target.display()
+                );
             }
 
-            // Write back if changes were made
-            if content != original_content {
-                let mut file = fs::File::create(path)?;
-                file.write_all(content.as_bytes())?;
+            if entry.file_type().is_dir() {
+                // Other directories are created with their files, so ones
+                // whose files are all left out don't show up empty
+                if fs::read_dir(path)?.next().is_none() {
+                    let resolved = resolve_inside(&root, &target)?;
+                    fs::create_dir_all(project_path.join(resolved))?;
+                }
+            } else {
+                let resolved = resolve_inside(&root, &target)?;
+                if entry.file_type().is_symlink() {
+                    copy_symlink(path, project_path, &resolved)?;
+                } else {
+                    let verbatim = filter.is_verbatim(relative_path);
+                    let target_path = project_path.join(resolved);
+                    self.render_file(path, &target, &target_path, verbatim)?;
+                }
             }
         }
+
+        Ok(())
     }
 
-    Ok(())
-}
+    /// Renders each component of a relative path
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if a component renders to an empty name
+    fn render_path(&self, relative_path: &Path) -> Result<Option<PathBuf>> {
+        let mut rendered = PathBuf::new();
+
+        for component in relative_path.iter() {
+            let component = component.to_string_lossy();
+            if !component.contains('{') {
+                rendered.push(component.as_ref());
+                continue;
+            }
 
-/// Processes template files by applying transformations
-///
-/// # Arguments
-///
-/// * `project_path` - Path to the project directory
-/// * `processor` - Function that processes each file's content
-///
-/// # Returns
-///
-/// Returns `Ok(())` if successful, or an error if file operations fail
-#[allow(dead_code)]
-pub fn process_template_files<F>(project_path: &Path, mut processor: F) -> Result<()>
-where
-    F: FnMut(&str) -> String,
-{
-    for entry in WalkDir::new(project_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-
-        // Skip binary files and target directory
-        if should_skip_path(path) {
-            continue;
+            let name = self.render_str(&relative_path.to_string_lossy(), &component)?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Ok(None);
+            }
+            if name == "." || name == ".." || name.contains(['/', '\\']) {
+                anyhow::bail!(
+                    "'{}' renders to '{}', which is not a valid file name",
+                    relative_path.display(),
+                    name
+                );
+            }
+            rendered.push(name);
         }
 
-        // Try to read file as text
-        if let Ok(content) = fs::read_to_string(path) {
-            let new_content = processor(&content);
+        Ok(Some(rendered))
+    }
 
-            // Write back if changes were made
-            if content != new_content {
-                let mut file = fs::File::create(path)?;
-                file.write_all(new_content.as_bytes())?;
+    /// Renders a text file to `target_path`, or copies it if it is binary or
+    /// `verbatim`
+    ///
+    /// A text file already at `target_path` is merged with the rendered one.
+    /// The file gets the permission bits of `source`, so scripts stay
+    /// executable.
+    fn render_file(
+        &self,
+        source: &Path,
+        target: &Path,
+        target_path: &Path,
+        verbatim: bool,
+    ) -> Result<()> {
+        // Skip verbatim and binary files and the target directory
+        let content = if verbatim || should_skip_path(target_path) || is_binary_file(source) {
+            None
+        } else {
+            // Files that aren't UTF-8 past the sniffed bytes are copied too
+            fs::read_to_string(source).ok()
+        };
+
+        // A symlink from an earlier layer is replaced, not written through
+        if target_path.is_symlink() {
+            fs::remove_file(target_path)?;
+        }
+
+        match content {
+            Some(content) => {
+                let mut rendered = self.render_str(&target.to_string_lossy(), &content)?;
+                if let Ok(existing) = fs::read_to_string(target_path) {
+                    rendered = merge_file(target, &existing, &rendered)?;
+                }
+                fs::write(target_path, rendered)
+                    .with_context(|| format!("Failed to write '{}'", target_path.display()))?;
+                fs::set_permissions(target_path, fs::metadata(source)?.permissions())
+                    .with_context(|| {
+                        format!("Failed to set permissions of '{}'", target_path.display())
+                    })?;
+            }
+            None => {
+                fs::copy(source, target_path)
+                    .with_context(|| format!("Failed to copy '{}'", source.display()))?;
             }
         }
+
+        Ok(())
     }
+}
 
-    Ok(())
+/// Formats a rendering error with the template name and line
+fn render_error(error: &minijinja::Error) -> String {
+    let location = match (error.name(), error.line()) {
+        (Some(name), Some(line)) => format!(" in {} (line {})", name, line),
+        (Some(name), None) => format!(" in {}", name),
+        _ => String::new(),
+    };
+    let detail = error
+        .detail()
+        .map(str::to_string)
+        .unwrap_or_else(|| error.kind().to_string());
+    format!("Failed to render template{}: {}", location, detail)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
+    use crate::template::manifest::MANIFEST_FILE;
     use tempfile::TempDir;
 
+    fn manifest_filter() -> PathFilter {
+        PathFilter::new(&[MANIFEST_FILE]).unwrap()
+    }
+
+    fn renderer() -> Renderer {
+        let values: Values = toml::from_str(
+            r#"
+project_name = "my-app"
+auth = true
+features = ["user_profile", "billing"]
+"#,
+        )
+        .unwrap();
+        Renderer::new(&values)
+    }
+
     #[test]
-    fn test_replace_placeholders() {
+    fn test_render_dir() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
-
-        // Create a test file with placeholder
-        fs::write(&file_path, "Project: {{project_name}}").unwrap();
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(template.join("src/{{project_name|snake_case}}_core")).unwrap();
+        fs::write(template.join("Cargo.toml"), "name = \"{{project_name}}\"\n").unwrap();
+        fs::write(
+            template.join("src/{{project_name|snake_case}}_core/mod.rs"),
+            "// core\n",
+        )
+        .unwrap();
+        fs::write(template.join(MANIFEST_FILE), "description = \"x\"").unwrap();
+        fs::write(template.join(".wenzetuignore"), "docs/\n").unwrap();
+        fs::create_dir_all(template.join("docs")).unwrap();
+        fs::write(template.join("docs/template.md"), "{{ missing }}").unwrap();
+        fs::create_dir_all(template.join("web/node_modules/pkg")).unwrap();
+        fs::write(template.join("web/node_modules/pkg/index.js"), "").unwrap();
+
+        let project = temp_dir.path().join("project");
+        renderer()
+            .render_dir(&template, &project, &manifest_filter())
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(project.join("Cargo.toml")).unwrap(),
+            "name = \"my-app\"\n"
+        );
+        assert!(project.join("src/my_app_core/mod.rs").exists());
+        assert!(!project.join(MANIFEST_FILE).exists());
+        assert!(!project.join(".wenzetuignore").exists());
+        assert!(!project.join("docs").exists());
+        assert!(!project.join("web").exists());
+    }
 
-        // Replace placeholders
-        replace_placeholders(temp_dir.path(), "my-app").unwrap();
+    #[test]
+    fn test_render_layers() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().join("base");
+        let layer = temp_dir.path().join("layer");
+        fs::create_dir_all(base.join("src")).unwrap();
+        fs::create_dir_all(&layer).unwrap();
+        fs::write(
+            base.join("Cargo.toml"),
+            "[package]\nname = \"{{project_name}}\"\n\n[dependencies]\naxum = \"0.8\"\n",
+        )
+        .unwrap();
+        fs::write(base.join(".gitignore"), "/target\n").unwrap();
+        fs::write(base.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(layer.join("Cargo.toml"), "[dependencies]\nsqlx = \"0.8\"\n").unwrap();
+        fs::write(layer.join(".gitignore"), "/target\n.env\n").unwrap();
+        fs::write(layer.join("README.md"), "# {{project_name}}\n").unwrap();
+
+        let project = temp_dir.path().join("project");
+        let renderer = renderer();
+        renderer
+            .render_dir(&base, &project, &manifest_filter())
+            .unwrap();
+        renderer
+            .render_dir(&layer, &project, &manifest_filter())
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(project.join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"my-app\"\n\n[dependencies]\naxum = \"0.8\"\nsqlx = \"0.8\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(project.join(".gitignore")).unwrap(),
+            "/target\n.env\n"
+        );
+        assert!(project.join("src/main.rs").exists());
+        assert_eq!(
+            fs::read_to_string(project.join("README.md")).unwrap(),
+            "# my-app\n"
+        );
+    }
 
-        // Verify replacement
-        let content = fs::read_to_string(&file_path).unwrap();
-        assert_eq!(content, "Project: my-app");
+    #[test]
+    fn test_empty_names_skip_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(template.join("{% if not auth %}auth{% endif %}")).unwrap();
+        fs::write(template.join("{% if not auth %}auth{% endif %}/mod.rs"), "").unwrap();
+        fs::write(template.join("{% if auth %}auth.rs{% endif %}"), "").unwrap();
+
+        let project = temp_dir.path().join("project");
+        renderer()
+            .render_dir(&template, &project, &manifest_filter())
+            .unwrap();
+
+        assert!(project.join("auth.rs").exists());
+        assert!(!project.join("auth").exists());
     }
 
     #[test]
-    fn test_replace_multiple_placeholders() {
+    fn test_name_collisions() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(&template).unwrap();
+        fs::write(template.join("my-app.rs"), "").unwrap();
+        fs::write(template.join("{{project_name}}.rs"), "").unwrap();
+
+        let err = renderer()
+            .render_dir(
+                &template,
+                &temp_dir.path().join("project"),
+                &manifest_filter(),
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("render to 'my-app.rs'"));
+
+        fs::remove_file(template.join("my-app.rs")).unwrap();
+        fs::write(template.join("{{'.' ~ '.'}}"), "").unwrap();
+        assert!(
+            renderer()
+                .render_dir(
+                    &template,
+                    &temp_dir.path().join("escape"),
+                    &manifest_filter()
+                )
+                .is_err()
+        );
+    }
 
-        // Create a test file with multiple placeholders
-        fs::write(&file_path, "Project: {{project_name}}\nAuthor: {{author}}").unwrap();
+    #[test]
+    fn test_conditionals_and_loops() {
+        let source = "\
+{% if auth %}
+mod auth;
+{% endif %}
+{% for feature in features %}
+mod {{ feature }};
+{% endfor %}
+";
+
+        assert_eq!(
+            renderer().render_str("main.rs", source).unwrap(),
+            "mod auth;\nmod user_profile;\nmod billing;\n"
+        );
+    }
 
-        // Create replacements map
-        let mut replacements = std::collections::HashMap::new();
-        replacements.insert("project_name".to_string(), "my-app".to_string());
-        replacements.insert("author".to_string(), "John Doe".to_string());
+    #[test]
+    fn test_eval_condition() {
+        let renderer = renderer();
+        assert!(renderer.eval_condition("auth").unwrap());
+        assert!(renderer.eval_condition("'billing' in features").unwrap());
+        assert!(!renderer.eval_condition("project_name == 'other'").unwrap());
+        assert!(renderer.eval_condition("missing").is_err());
+    }
 
-        // Replace placeholders
-        replace_multiple_placeholders(temp_dir.path(), &replacements).unwrap();
+    #[test]
+    fn test_case_filters() {
+        let source = "{{ project_name | snake_case }} {{ project_name | pascal_case }} {{ 'MyApp' | kebab_case }}";
+        assert_eq!(
+            renderer().render_str("lib.rs", source).unwrap(),
+            "my_app MyApp my-app"
+        );
+    }
 
-        // Verify replacement
-        let content = fs::read_to_string(&file_path).unwrap();
-        assert_eq!(content, "Project: my-app\nAuthor: John Doe");
+    #[test]
+    fn test_escaped_braces() {
+        let source = r#"{{ "{{" }} count {{ "}}" }} {% raw %}<p>{{ name }}</p>{% endraw %}"#;
+        assert_eq!(
+            renderer().render_str("app.vue", source).unwrap(),
+            "{{ count }} <p>{{ name }}</p>"
+        );
     }
 
     #[test]
-    fn test_no_replacement_needed() {
+    fn test_errors_name_the_file() {
+        let err = renderer()
+            .render_str("src/main.rs", "line one\n{{ missing }}")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("src/main.rs"));
+        assert!(err.contains("line 2"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_modes_and_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(template.join("scripts")).unwrap();
+        fs::create_dir_all(template.join("config")).unwrap();
+        let script = template.join("scripts/{{project_name}}.sh");
+        fs::write(&script, "#!/bin/sh\necho {{ project_name }}\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(template.join("config/shared.toml"), "").unwrap();
+        symlink(
+            "../config/shared.toml",
+            template.join("scripts/shared.toml"),
+        )
+        .unwrap();
+        symlink("config", template.join("settings")).unwrap();
+
+        let project = temp_dir.path().join("project");
+        renderer()
+            .render_dir(&template, &project, &manifest_filter())
+            .unwrap();
+
+        let script = project.join("scripts/my-app.sh");
+        assert_eq!(
+            fs::read_to_string(&script).unwrap(),
+            "#!/bin/sh\necho my-app\n"
+        );
+        let mode = fs::metadata(&script).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            fs::read_link(project.join("scripts/shared.toml")).unwrap(),
+            Path::new("../config/shared.toml")
+        );
+        assert_eq!(
+            fs::read_link(project.join("settings")).unwrap(),
+            Path::new("config")
+        );
+
+        // Links leaving the project are refused
+        symlink("../../etc/passwd", template.join("scripts/passwd")).unwrap();
+        let err = renderer()
+            .render_dir(
+                &template,
+                &temp_dir.path().join("escape"),
+                &manifest_filter(),
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("points outside the project"));
+    }
 
-        // Create a test file without placeholders
-        let original_content = "No placeholders here";
-        fs::write(&file_path, original_content).unwrap();
+    #[cfg(unix)]
+    #[test]
+    fn test_layers_cant_write_through_symlinks() {
+        use std::os::unix::fs::symlink;
 
-        // Try to replace placeholders
-        replace_placeholders(temp_dir.path(), "my-app").unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().join("base");
+        let layer = temp_dir.path().join("layer");
+        fs::create_dir_all(base.join("a")).unwrap();
+        fs::create_dir_all(layer.join("a/up/x")).unwrap();
+        symlink("..", base.join("a/up")).unwrap();
+        fs::write(layer.join("a/up/x/pwned.txt"), "x").unwrap();
+
+        let project = temp_dir.path().join("out/project");
+        renderer()
+            .render_dir(&base, &project, &manifest_filter())
+            .unwrap();
+
+        // `a/up/x` is `x` at the root of the project, so a link to `..`
+        // there leaves it
+        fs::remove_dir_all(layer.join("a/up/x")).unwrap();
+        symlink("..", layer.join("a/up/x")).unwrap();
+        let err = renderer()
+            .render_dir(&layer, &project, &manifest_filter())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("points outside the project"));
+
+        // Nor can a file be written through a link left by an earlier layer
+        fs::remove_file(layer.join("a/up/x")).unwrap();
+        fs::create_dir_all(layer.join("a/up/x")).unwrap();
+        fs::write(layer.join("a/up/x/pwned.txt"), "x").unwrap();
+        symlink("..", project.join("x")).unwrap();
+        let err = renderer()
+            .render_dir(&layer, &project, &manifest_filter())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("leads outside the project through a symlink"));
+        assert!(!temp_dir.path().join("out/pwned.txt").exists());
+    }
 
-        // Verify content unchanged
-        let content = fs::read_to_string(&file_path).unwrap();
-        assert_eq!(content, original_content);
+    #[test]
+    fn test_binary_and_verbatim_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(template.join("static")).unwrap();
+        let icon = b"\x00\x00\x01\x00{{ missing }}";
+        fs::write(template.join("favicon.ico"), icon).unwrap();
+        fs::write(template.join("static/app.min.js"), "x=\"{{y}}\"").unwrap();
+        fs::write(template.join("main.rs"), "// {{ project_name }}").unwrap();
+
+        let filter = PathFilter::new(&[MANIFEST_FILE])
+            .unwrap()
+            .with_verbatim(&["static/**"])
+            .unwrap();
+        let project = temp_dir.path().join("project");
+        renderer().render_dir(&template, &project, &filter).unwrap();
+
+        assert_eq!(fs::read(project.join("favicon.ico")).unwrap(), icon);
+        assert_eq!(
+            fs::read_to_string(project.join("static/app.min.js")).unwrap(),
+            "x=\"{{y}}\""
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("main.rs")).unwrap(),
+            "// my-app"
+        );
     }
 
     #[test]
-    fn test_process_template_files() {
+    fn test_no_replacement_needed() {
         let temp_dir = TempDir::new().unwrap();
-        let file_path = temp_dir.path().join("test.txt");
+        let template = temp_dir.path().join("template");
+        fs::create_dir_all(&template).unwrap();
 
-        // Create a test file
-        fs::write(&file_path, "hello world").unwrap();
+        // Create a test file without placeholders
+        let original_content = "No placeholders here";
+        fs::write(template.join("test.txt"), original_content).unwrap();
 
-        // Process with uppercase transformation
-        process_template_files(temp_dir.path(), |content| content.to_uppercase()).unwrap();
+        let project = temp_dir.path().join("project");
+        renderer()
+            .render_dir(&template, &project, &manifest_filter())
+            .unwrap();
 
-        // Verify transformation
-        let content = fs::read_to_string(&file_path).unwrap();
-        assert_eq!(content, "HELLO WORLD");
+        // Verify content unchanged
+        let content = fs::read_to_string(project.join("test.txt")).unwrap();
+        assert_eq!(content, original_content);
     }
 }
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Name of the provenance file written into generated projects
+pub const PROVENANCE_FILE: &str = ".wenzetu.toml";
+
+/// Records where a generated project's template came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub template: TemplateRecord,
+    /// Every template the project was rendered from, in render order: the
+    /// parents templates extend, the template itself and its layers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerRecord>,
+}
+
+/// The `[template]` table of the provenance file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateRecord {
+    /// Repository URL or local path
+    pub source: String,
+    /// Template name (or path for local templates)
+    pub name: String,
+    /// Branch, tag or commit that was requested
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// Commit the template was taken from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Version of wenzetu that generated the project
+    pub wenzetu_version: String,
+}
+
+/// A `[[layers]]` entry of the provenance file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerRecord {
+    /// Repository URL or local path
+    pub source: String,
+    /// Template name (or path for local templates)
+    pub name: String,
+    /// Branch, tag or commit that was requested
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// Commit the template was taken from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl Provenance {
+    /// Creates a provenance record for the running wenzetu version
+    pub fn new(source: &str, name: &str, reference: Option<&str>, commit: Option<String>) -> Self {
+        Self {
+            template: TemplateRecord {
+                source: source.to_string(),
+                name: name.to_string(),
+                reference: reference.map(str::to_string),
+                commit,
+                wenzetu_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            layers: Vec::new(),
+        }
+    }
+
+    /// The template of this record, as an entry of another project's
+    /// `layers`
+    pub fn layer(&self) -> LayerRecord {
+        LayerRecord {
+            source: self.template.source.clone(),
+            name: self.template.name.clone(),
+            reference: self.template.reference.clone(),
+            commit: self.template.commit.clone(),
+        }
+    }
+
+    /// Writes the provenance file into a project directory
+    ///
+    /// # Arguments
+    ///
+    /// * `project_path` - Path to the project directory
+    pub fn write(&self, project_path: &Path) -> Result<()> {
+        let body = toml::to_string(self).context("Failed to serialize template provenance")?;
+        let content = format!(
+            "# Generated by wenzetu. Records the template this project was created from.\n\n{}",
+            body
+        );
+        fs::write(project_path.join(PROVENANCE_FILE), content)
+            .context("Failed to write template provenance")?;
+        Ok(())
+    }
+
+    /// Reads the provenance file from a project directory
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the project has no provenance file
+    #[allow(dead_code)]
+    pub fn read(project_path: &Path) -> Result<Option<Self>> {
+        let path = project_path.join(PROVENANCE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path).context("Failed to read template provenance")?;
+        let provenance = toml::from_str(&content).context("Failed to parse template provenance")?;
+        Ok(Some(provenance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut provenance = Provenance::new(
+            "https://github.com/user/repo",
+            "default",
+            Some("v1.0.0"),
+            Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+        );
+        let parent = Provenance::new("/srv/templates", "base", None, None);
+        provenance.layers = vec![parent.layer(), provenance.layer()];
+
+        provenance.write(temp_dir.path()).unwrap();
+
+        let content = fs::read_to_string(temp_dir.path().join(PROVENANCE_FILE)).unwrap();
+        assert!(content.contains("[template]"));
+        assert!(content.contains("ref = \"v1.0.0\""));
+        assert!(content.contains("commit = \"0123456789abcdef0123456789abcdef01234567\""));
+        assert!(content.contains("[[layers]]\nsource = \"/srv/templates\"\nname = \"base\""));
+
+        assert_eq!(Provenance::read(temp_dir.path()).unwrap(), Some(provenance));
+    }
+
+    #[test]
+    fn test_local_template_omits_commit() {
+        let temp_dir = TempDir::new().unwrap();
+        Provenance::new("/srv/templates", "default", None, None)
+            .write(temp_dir.path())
+            .unwrap();
+
+        let content = fs::read_to_string(temp_dir.path().join(PROVENANCE_FILE)).unwrap();
+        assert!(!content.contains("commit"));
+        assert!(!content.contains("ref"));
+    }
+}
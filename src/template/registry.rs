@@ -0,0 +1,449 @@
+use anyhow::{Context, Result};
+use colored::*;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::source::{TemplateSource, local_path};
+use super::version::parse_version;
+use crate::utils::config::Config;
+use crate::utils::http::{http_client, send};
+use crate::utils::validate_reference;
+
+/// A registry index listing templates by name
+///
+/// ```toml
+/// [[templates]]
+/// name = "axum-api"
+/// description = "JSON API with Axum"
+/// tags = ["api", "axum"]
+/// source = "https://github.com/acme/templates"
+/// template = "api"
+/// version = "1.2.0"
+/// ref = "v1.2.0"
+/// sha256 = "3b4c...e9"
+/// ```
+///
+/// A name can be listed once per version.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryIndex {
+    #[serde(default)]
+    pub templates: Vec<RegistryEntry>,
+}
+
+/// A template listed in a registry index
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryEntry {
+    /// Name the template is created with (`create-app -t <name>`)
+    pub name: String,
+    /// What the template is for
+    pub description: Option<String>,
+    /// Keywords matched by `templates search`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Repository holding the template, in any form `--repo` accepts
+    pub source: String,
+    /// Template directory in the repository (defaults to `name`)
+    pub template: Option<String>,
+    /// Semver version of this entry
+    pub version: Option<String>,
+    /// Branch, tag or commit SHA to use (defaults to `v<version>` for
+    /// versioned entries)
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// Expected sha256 of the repository archive
+    pub sha256: Option<String>,
+}
+
+impl RegistryEntry {
+    /// Directory name of the template in its repository
+    pub fn template_name(&self) -> &str {
+        self.template.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Branch, tag or commit SHA the entry points at, if any
+    pub fn reference(&self) -> Option<String> {
+        self.reference
+            .clone()
+            .or_else(|| self.version.as_ref().map(|version| format!("v{}", version)))
+    }
+
+    /// Where the template comes from
+    pub fn template_source(&self) -> Result<TemplateSource> {
+        TemplateSource::parse(Some(&self.source), self.template_name())
+    }
+
+    /// Returns `true` if every word of the query appears in the name,
+    /// description or tags (ignoring case)
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = format!(
+            "{} {} {}",
+            self.name,
+            self.description.as_deref().unwrap_or_default(),
+            self.tags.join(" ")
+        )
+        .to_lowercase();
+
+        query
+            .split_whitespace()
+            .all(|word| haystack.contains(&word.to_lowercase()))
+    }
+}
+
+/// The templates of every configured registry index
+#[derive(Debug, Default)]
+pub struct Registry {
+    entries: Vec<RegistryEntry>,
+}
+
+impl Registry {
+    /// Loads the registry indexes given on the command line, then those
+    /// listed under `registries` in the config file
+    ///
+    /// # Arguments
+    ///
+    /// * `locations` - Index files or URLs from the command line
+    /// * `offline` - Skip indexes that would have to be downloaded
+    ///
+    /// # Returns
+    ///
+    /// Returns the registry, or an error if an index can't be read. Indexes
+    /// given as URLs that can't be downloaded are skipped with a warning.
+    pub fn load(locations: &[String], offline: bool) -> Result<Self> {
+        let mut all = locations.to_vec();
+        all.extend(Config::load()?.registries);
+
+        let mut registry = Self::default();
+        for location in &all {
+            if offline && is_url(location) {
+                continue;
+            }
+            if let Some(index) = load_index(location)? {
+                registry.entries.extend(index.templates);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Returns `true` if no index lists any template
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds a template by name
+    ///
+    /// When the name is listed with versions, the newest version matching
+    /// `requirement` (or the newest overall) is picked. Otherwise the first
+    /// index listing the name wins, and the requirement is left to the
+    /// caller.
+    ///
+    /// # Returns
+    ///
+    /// Returns the entry, `None` if no index lists the name, or an error if
+    /// no listed version matches
+    pub fn find(
+        &self,
+        name: &str,
+        requirement: Option<&VersionReq>,
+    ) -> Result<Option<&RegistryEntry>> {
+        let listed: Vec<&RegistryEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.name == name)
+            .collect();
+        let mut versioned: Vec<(Version, &RegistryEntry)> = listed
+            .iter()
+            .filter_map(|entry| {
+                let version = parse_version(entry.version.as_deref()?)?;
+                Some((version, *entry))
+            })
+            .collect();
+        if versioned.is_empty() {
+            return Ok(listed.first().copied());
+        }
+
+        versioned.sort_by(|a, b| a.0.cmp(&b.0));
+        let newest = versioned
+            .iter()
+            .rev()
+            .find(|(version, _)| requirement.is_none_or(|req| req.matches(version)));
+        if let Some((_, entry)) = newest {
+            return Ok(Some(*entry));
+        }
+
+        anyhow::bail!(
+            "No version of template '{}' in the registry matches {}. Available versions: {}",
+            name,
+            requirement.map(ToString::to_string).unwrap_or_default(),
+            versioned
+                .iter()
+                .map(|(version, _)| version.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    /// Returns the templates matching a search query, sorted by name
+    pub fn search(&self, query: &str) -> Vec<&RegistryEntry> {
+        let mut found: Vec<&RegistryEntry> = Vec::new();
+        for entry in self.entries.iter().filter(|entry| entry.matches(query)) {
+            if found.iter().all(|other| other.name != entry.name) {
+                found.push(entry);
+            }
+        }
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found
+    }
+}
+
+/// Reads one registry index from a file or an `http(s)://` URL
+///
+/// Relative paths in the `source` of a local index are relative to the
+/// index file. An index downloaded from a URL can only list remote
+/// sources, never paths on this machine.
+///
+/// # Returns
+///
+/// Returns the index, `None` if a URL index can't be downloaded, or an
+/// error if the index is invalid
+fn load_index(location: &str) -> Result<Option<RegistryIndex>> {
+    let (content, base_dir) = if is_url(location) {
+        match download_index(location) {
+            Ok(content) => (content, None),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("Warning: skipping registry index: {:#}", e).yellow()
+                );
+                return Ok(None);
+            }
+        }
+    } else {
+        let path = local_path(location)?.unwrap_or_else(|| location.into());
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read registry index '{}'", path.display()))?;
+        let base_dir = std::path::absolute(&path)?.parent().map(Path::to_path_buf);
+        (content, base_dir)
+    };
+
+    let mut index: RegistryIndex = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse registry index '{}'", location))?;
+
+    for entry in &mut index.templates {
+        if entry.name.trim().is_empty() {
+            anyhow::bail!(
+                "Registry index '{}' lists a template without a name",
+                location
+            );
+        }
+        if let Some(version) = &entry.version
+            && parse_version(version).is_none()
+        {
+            anyhow::bail!(
+                "Invalid version '{}' for template '{}' in registry index '{}'",
+                version,
+                entry.name,
+                location
+            );
+        }
+        if let Some(reference) = &entry.reference {
+            validate_reference(reference).with_context(|| {
+                format!(
+                    "Invalid ref for template '{}' in registry index '{}'",
+                    entry.name, location
+                )
+            })?;
+        }
+        match &base_dir {
+            Some(base_dir) if entry.source.starts_with("./") || entry.source.starts_with("../") => {
+                entry.source = resolve_relative(base_dir, &entry.source)
+                    .to_string_lossy()
+                    .to_string();
+            }
+            None if local_path(&entry.source)?.is_some() => anyhow::bail!(
+                "Registry index '{}' lists the local path '{}' for template '{}'. Indexes downloaded from a URL can only list remote repositories.",
+                location,
+                entry.source,
+                entry.name
+            ),
+            _ => {}
+        }
+    }
+
+    Ok(Some(index))
+}
+
+/// Downloads a registry index
+fn download_index(location: &str) -> Result<String> {
+    let response = send(http_client()?.get(location))
+        .with_context(|| format!("Failed to download registry index '{}'", location))?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to download registry index '{}' (HTTP {})",
+            location,
+            response.status()
+        );
+    }
+    Ok(response.text()?)
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+/// Joins a relative path onto a directory, resolving `.` and `..`
+fn resolve_relative(base_dir: &Path, relative: &str) -> PathBuf {
+    let mut path = base_dir.to_path_buf();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            other => path.push(other),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+    use tempfile::TempDir;
+
+    const INDEX: &str = r#"
+[[templates]]
+name = "axum-api"
+description = "JSON API with Axum"
+tags = ["api", "axum"]
+source = "./templates"
+template = "api"
+ref = "v1.2.0"
+
+[[templates]]
+name = "blog"
+description = "Markdown blog"
+source = "acme/templates"
+"#;
+
+    #[test]
+    fn test_load_and_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("index.toml");
+        fs::write(&path, INDEX).unwrap();
+
+        let server = StubServer::start(|_| {
+            StubResponse::ok("[[templates]]\nname = \"blog\"\nsource = \"other/blog\"\n")
+        });
+
+        // Earlier indexes win when names clash
+        let registry =
+            Registry::load(&[path.to_string_lossy().to_string(), server.url()], false).unwrap();
+
+        let entry = registry.find("axum-api", None).unwrap().unwrap();
+        assert_eq!(entry.template_name(), "api");
+        assert_eq!(entry.reference.as_deref(), Some("v1.2.0"));
+        assert_eq!(
+            entry.source,
+            temp_dir.path().join("templates").to_string_lossy()
+        );
+        let blog = registry.find("blog", None).unwrap().unwrap();
+        assert_eq!(blog.source, "acme/templates");
+        assert!(registry.find("missing", None).unwrap().is_none());
+
+        let names = |query| -> Vec<String> {
+            registry
+                .search(query)
+                .iter()
+                .map(|entry| entry.name.clone())
+                .collect()
+        };
+        assert_eq!(names("AXUM"), vec!["axum-api"]);
+        assert_eq!(names("api json"), vec!["axum-api"]);
+        assert_eq!(names("blog"), vec!["blog"]);
+        assert!(names("graphql").is_empty());
+
+        // Remote indexes are skipped offline
+        let offline = Registry::load(&[server.url()], true).unwrap();
+        assert!(offline.is_empty());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_find_version() {
+        let entry = |version: &str| RegistryEntry {
+            name: "default".to_string(),
+            description: None,
+            tags: Vec::new(),
+            source: "acme/templates".to_string(),
+            template: None,
+            version: Some(version.to_string()),
+            reference: None,
+            sha256: None,
+        };
+        let registry = Registry {
+            entries: vec![entry("1.2.0"), entry("1.4.1"), entry("2.0.0")],
+        };
+        let find = |req: Option<&str>| {
+            registry
+                .find(
+                    "default",
+                    req.map(|r| VersionReq::parse(r).unwrap()).as_ref(),
+                )
+                .map(|entry| entry.unwrap().reference())
+        };
+
+        assert_eq!(find(Some("^1.2")).unwrap().as_deref(), Some("v1.4.1"));
+        assert_eq!(find(None).unwrap().as_deref(), Some("v2.0.0"));
+        let err = find(Some("^3")).unwrap_err().to_string();
+        assert!(err.contains("Available versions: 1.2.0, 1.4.1, 2.0.0"));
+    }
+
+    #[test]
+    fn test_invalid_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("index.toml");
+        fs::write(&path, "[[templates]]\nname = \"x\"\n").unwrap();
+
+        let err = Registry::load(&[path.to_string_lossy().to_string()], false).unwrap_err();
+        assert!(err.to_string().contains("Failed to parse registry index"));
+
+        // Refs that git could read as options are refused
+        fs::write(
+            &path,
+            "[[templates]]\nname = \"x\"\nsource = \"acme/templates\"\nref = \"--upload-pack=x\"\n",
+        )
+        .unwrap();
+        let err = Registry::load(&[path.to_string_lossy().to_string()], false).unwrap_err();
+        assert!(err.to_string().contains("Invalid ref"));
+    }
+
+    #[test]
+    fn test_remote_index_checks() {
+        // Remote indexes can't point at local directories
+        for source in [
+            "./templates",
+            "../templates",
+            "/srv/templates",
+            "~/templates",
+            "file:///srv",
+        ] {
+            let body = format!("[[templates]]\nname = \"x\"\nsource = \"{}\"\n", source);
+            let server = StubServer::start(move |_| StubResponse::ok(body.clone()));
+            let err = Registry::load(&[server.url()], false).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("can only list remote repositories")
+            );
+        }
+
+        // Indexes that can't be downloaded are skipped
+        let server = StubServer::start(|_| StubResponse::status(404));
+        let registry = Registry::load(&[server.url()], false).unwrap();
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use super::archive::extract_tarball;
+use super::catalog::repository_root;
+use super::clone::{clone_template, git_cache_key, is_bare_repo, is_git_url, strip_git_prefix};
+use super::download::{DownloadOptions, download_template, find_template_dir, normalize_repo_url};
+use super::provenance::Provenance;
+use super::verify::ArchiveVerifier;
+
+/// Repository used when `--repo` is not given
+pub const DEFAULT_REPO: &str = "erickweyunga/uncovr-templates";
+
+/// Branch used when neither `--branch` nor `--ref` is given
+pub const DEFAULT_REF: &str = "main";
+
+/// Where a template comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// Repository on a remote git host (full URL)
+    Remote(String),
+    /// Any git repository, fetched with `git clone`
+    Git(String),
+    /// Local directory laid out like a template repository
+    LocalRepo(PathBuf),
+    /// Local tarball of a template repository
+    LocalArchive(PathBuf),
+    /// A single template directory on disk
+    LocalTemplate(PathBuf),
+}
+
+impl TemplateSource {
+    /// Determines the template source from the `--repo` and `--template` options
+    ///
+    /// A template given as a path (`./dir`, `../dir`, `/dir`, `~/dir` or
+    /// `file://`) is used directly. A repository given as a path or
+    /// `file://` URL is read from disk, either as a directory, a bare git
+    /// repository or a `.tar`/`.tar.gz` archive. `git@host:path`, `ssh://`,
+    /// `git://` and `git+https://` URLs are cloned. Anything else is a
+    /// remote repository downloaded as an archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo` - Optional repository URL, GitHub shorthand or local path
+    /// * `template` - Template name or path
+    ///
+    /// # Returns
+    ///
+    /// Returns the resolved source, or an error if a local path doesn't exist
+    pub fn parse(repo: Option<&str>, template: &str) -> Result<Self> {
+        if let Some(path) = local_path(template)? {
+            if repo.is_some() {
+                anyhow::bail!("--repo can't be combined with a template path");
+            }
+            if !path.is_dir() {
+                anyhow::bail!("Template directory '{}' does not exist", path.display());
+            }
+            return Ok(Self::LocalTemplate(path));
+        }
+
+        let repo = repo.unwrap_or(DEFAULT_REPO);
+        if is_git_url(repo) {
+            return Ok(Self::Git(strip_git_prefix(repo).to_string()));
+        }
+
+        match local_path(repo)? {
+            Some(path) if is_bare_repo(&path) => Ok(Self::Git(path.to_string_lossy().to_string())),
+            Some(path) if path.is_dir() => Ok(Self::LocalRepo(path)),
+            Some(path) if path.is_file() => Ok(Self::LocalArchive(path)),
+            Some(path) => anyhow::bail!("Template repository '{}' does not exist", path.display()),
+            None => Ok(Self::Remote(normalize_repo_url(repo))),
+        }
+    }
+
+    /// Host of a remote or git repository, with the port if any
+    ///
+    /// Returns `None` for templates on disk, including local bare
+    /// repositories.
+    pub fn host(&self) -> Option<String> {
+        match self {
+            Self::Remote(url) => {
+                let url = Url::parse(url).ok()?;
+                let host = url.host_str()?;
+                Some(match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                })
+            }
+            Self::Git(url) => {
+                let key = git_cache_key(url);
+                let host = key.split('/').next()?;
+                (host != "local").then(|| host.to_string())
+            }
+            Self::LocalRepo(_) | Self::LocalArchive(_) | Self::LocalTemplate(_) => None,
+        }
+    }
+}
+
+/// A template located on disk, ready to be rendered into a project
+#[derive(Debug)]
+pub struct FetchedTemplate {
+    /// Template directory
+    pub dir: PathBuf,
+    /// Record of where the template came from
+    pub provenance: Provenance,
+    /// Keeps extracted archives alive until the project is generated
+    _temp_dir: Option<TempDir>,
+}
+
+/// Fetches a template from its source
+///
+/// Remote and git templates are read from the local cache, local ones in
+/// place. Nothing is copied yet.
+///
+/// # Arguments
+///
+/// * `source` - Where the template comes from
+/// * `reference` - Branch, tag or commit SHA to use for git repositories
+/// * `template` - Template name (ignored for [`TemplateSource::LocalTemplate`])
+/// * `options` - Options for remote downloads
+///
+/// # Returns
+///
+/// Returns the template directory and a record of what was fetched
+/// (including the resolved commit for git repositories), or an error if the
+/// template can't be fetched
+pub fn fetch_template(
+    source: &TemplateSource,
+    reference: &str,
+    template: &str,
+    options: &DownloadOptions,
+) -> Result<FetchedTemplate> {
+    let is_archive = matches!(
+        source,
+        TemplateSource::Remote(_) | TemplateSource::LocalArchive(_)
+    );
+    if options.verify.is_required() && !is_archive {
+        anyhow::bail!(
+            "--sha256 and --signature only apply to template archives, not git clones or local directories"
+        );
+    }
+
+    let fetched = |dir, provenance| FetchedTemplate {
+        dir,
+        provenance,
+        _temp_dir: None,
+    };
+
+    match source {
+        TemplateSource::Remote(url) => {
+            let (commit, dir) = download_template(url, reference, template, options)?;
+            Ok(fetched(
+                dir,
+                Provenance::new(url, template, Some(reference), Some(commit)),
+            ))
+        }
+        TemplateSource::Git(url) => {
+            let (commit, dir) = clone_template(url, reference, template, options)?;
+            Ok(fetched(
+                dir,
+                Provenance::new(url, template, Some(reference), Some(commit)),
+            ))
+        }
+        TemplateSource::LocalRepo(repo) => {
+            let dir = find_template_dir(repo, template)?;
+            Ok(fetched(
+                dir,
+                Provenance::new(&repo.to_string_lossy(), template, None, None),
+            ))
+        }
+        TemplateSource::LocalArchive(file) => {
+            let bytes = fs::read(file)
+                .with_context(|| format!("Failed to read archive '{}'", file.display()))?;
+            let signature = options.verify.trusted_signature()?;
+            let mut verifier = ArchiveVerifier::new(&options.verify, signature.as_ref())?;
+            verifier.update(&bytes);
+            verifier.finish()?;
+
+            let temp_dir = tempfile::tempdir()?;
+            extract_tarball(&bytes, temp_dir.path())?;
+            let dir = find_template_dir(&repository_root(temp_dir.path()), template)?;
+            Ok(FetchedTemplate {
+                dir,
+                provenance: Provenance::new(&file.to_string_lossy(), template, None, None),
+                _temp_dir: Some(temp_dir),
+            })
+        }
+        TemplateSource::LocalTemplate(dir) => {
+            let path = dir.to_string_lossy();
+            Ok(fetched(
+                dir.clone(),
+                Provenance::new(&path, &path, None, None),
+            ))
+        }
+    }
+}
+
+/// Interprets a value as a local path, if it looks like one
+///
+/// # Returns
+///
+/// Returns `Some(path)` for `file://` URLs and for values starting with
+/// `./`, `../`, `/` or `~/`, `None` otherwise
+pub fn local_path(value: &str) -> Result<Option<PathBuf>> {
+    if value.starts_with("file://") {
+        let url = Url::parse(value).with_context(|| format!("Invalid file URL: {}", value))?;
+        let path = url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("Invalid file URL: {}", value))?;
+        return Ok(Some(path));
+    }
+
+    if let Some(rest) = value.strip_prefix("~/") {
+        let home = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the home directory"))?;
+        return Ok(Some(home.join(rest)));
+    }
+
+    let is_path = value.starts_with("./")
+        || value.starts_with("../")
+        || Path::new(value).is_absolute()
+        || value == "."
+        || value == "..";
+    Ok(is_path.then(|| PathBuf::from(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::tarball;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_remote() {
+        assert_eq!(
+            TemplateSource::parse(None, "default").unwrap(),
+            TemplateSource::Remote(format!("https://github.com/{}", DEFAULT_REPO))
+        );
+        assert_eq!(
+            TemplateSource::parse(Some("user/repo"), "default").unwrap(),
+            TemplateSource::Remote("https://github.com/user/repo".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse(Some("git@example.com:team/templates.git"), "default").unwrap(),
+            TemplateSource::Git("git@example.com:team/templates.git".to_string())
+        );
+        assert_eq!(
+            TemplateSource::parse(Some("git+https://git.example.com/templates"), "default")
+                .unwrap(),
+            TemplateSource::Git("https://git.example.com/templates".to_string())
+        );
+    }
+
+    #[test]
+    fn test_host() {
+        let host = |repo| TemplateSource::parse(Some(repo), "default").unwrap().host();
+        assert_eq!(host("user/repo").as_deref(), Some("github.com"));
+        assert_eq!(
+            host("https://git.example.com:8443/team/templates").as_deref(),
+            Some("git.example.com:8443")
+        );
+        assert_eq!(
+            host("git@example.com:team/templates.git").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            host("ssh://git@example.com:2222/team/templates.git").as_deref(),
+            Some("example.com:2222")
+        );
+        assert_eq!(
+            TemplateSource::Git("/srv/git/templates.git".to_string()).host(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_local() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+        let archive = dir.join("templates.tar.gz");
+        fs::write(&archive, tarball(&[("default/a.txt", "a")])).unwrap();
+
+        let dir_str = dir.to_str().unwrap();
+        assert_eq!(
+            TemplateSource::parse(None, dir_str).unwrap(),
+            TemplateSource::LocalTemplate(dir.clone())
+        );
+        assert_eq!(
+            TemplateSource::parse(Some(dir_str), "default").unwrap(),
+            TemplateSource::LocalRepo(dir.clone())
+        );
+        assert_eq!(
+            TemplateSource::parse(Some(&format!("file://{}", archive.display())), "default")
+                .unwrap(),
+            TemplateSource::LocalArchive(archive)
+        );
+
+        assert!(TemplateSource::parse(Some("./does-not-exist"), "default").is_err());
+        assert!(TemplateSource::parse(None, "./does-not-exist").is_err());
+        assert!(TemplateSource::parse(Some("user/repo"), dir_str).is_err());
+    }
+
+    #[test]
+    fn test_fetch_local_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("templates.tar.gz");
+        fs::write(
+            &archive,
+            tarball(&[
+                ("templates/default/Cargo.toml", "name"),
+                ("templates/other/Cargo.toml", "other"),
+            ]),
+        )
+        .unwrap();
+
+        let source = TemplateSource::LocalArchive(archive);
+        let fetched =
+            fetch_template(&source, "main", "default", &DownloadOptions::default()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(fetched.dir.join("Cargo.toml")).unwrap(),
+            "name"
+        );
+    }
+
+    #[test]
+    fn test_fetch_local_repo_and_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir_all(repo.join("default/src")).unwrap();
+        fs::write(repo.join("default/src/main.rs"), "fn main() {}").unwrap();
+
+        let options = DownloadOptions::default();
+        let fetched = fetch_template(
+            &TemplateSource::LocalRepo(repo.clone()),
+            "main",
+            "default",
+            &options,
+        )
+        .unwrap();
+        assert!(fetched.dir.join("src/main.rs").exists());
+
+        let fetched = fetch_template(
+            &TemplateSource::LocalTemplate(repo.join("default")),
+            "main",
+            "ignored",
+            &options,
+        )
+        .unwrap();
+        assert!(fetched.dir.join("src/main.rs").exists());
+
+        assert!(
+            fetch_template(
+                &TemplateSource::LocalRepo(repo.clone()),
+                "main",
+                "missing",
+                &options
+            )
+            .is_err()
+        );
+
+        // Only top-level directories are templates
+        fs::create_dir_all(repo.join(".github")).unwrap();
+        for name in ["src", ".github/.."] {
+            assert!(
+                fetch_template(
+                    &TemplateSource::LocalRepo(repo.clone()),
+                    "main",
+                    name,
+                    &options
+                )
+                .is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use dialoguer::{Confirm, Input, Select};
+use std::fs;
+use std::path::Path;
+
+use super::manifest::{Values, Variable, VariableType};
+
+/// Collects variable values given on the command line
+///
+/// Values from `--var` override the same values in the vars file.
+///
+/// # Arguments
+///
+/// * `vars` - `key=value` arguments
+/// * `vars_file` - Optional TOML file of `key = value` pairs
+///
+/// # Returns
+///
+/// Returns the values, or an error if an argument or the file is malformed
+pub fn provided_values(vars: &[String], vars_file: Option<&Path>) -> Result<Values> {
+    let mut values = match vars_file {
+        Some(path) => load_vars_file(path)?,
+        None => Values::new(),
+    };
+
+    for var in vars {
+        let (name, value) = parse_var(var)?;
+        values.insert(name, toml::Value::String(value));
+    }
+
+    Ok(values)
+}
+
+/// Parses a `key=value` argument
+fn parse_var(var: &str) -> Result<(String, String)> {
+    match var.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => anyhow::bail!("Invalid --var '{}'. Expected key=value", var),
+    }
+}
+
+/// Reads a TOML file of `key = value` pairs
+fn load_vars_file(path: &Path) -> Result<Values> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read vars file '{}'", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse vars file '{}'", path.display()))
+}
+
+/// Asks for a variable's value on the terminal
+///
+/// Bools are asked as yes/no, variables with choices as a selection and
+/// everything else as text, validated as it is typed.
+pub fn prompt_value(variable: &Variable) -> Result<toml::Value> {
+    let label = match &variable.description {
+        Some(description) => format!("{} ({})", description, variable.name),
+        None => variable.name.clone(),
+    };
+
+    if variable.kind == VariableType::Bool {
+        let default = variable
+            .default
+            .as_ref()
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false);
+        let answer = Confirm::new()
+            .with_prompt(label)
+            .default(default)
+            .interact()?;
+        return Ok(toml::Value::Boolean(answer));
+    }
+
+    if !variable.choices.is_empty() {
+        let default = variable
+            .default
+            .as_ref()
+            .and_then(toml::Value::as_str)
+            .and_then(|d| variable.choices.iter().position(|c| c == d))
+            .unwrap_or(0);
+        let index = Select::new()
+            .with_prompt(label)
+            .items(&variable.choices)
+            .default(default)
+            .interact()?;
+        return Ok(toml::Value::String(variable.choices[index].clone()));
+    }
+
+    let mut input = Input::<String>::new().with_prompt(label);
+    if let Some(default) = &variable.default {
+        let text = match default {
+            toml::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        input = input.default(text);
+    }
+    let answer = input
+        .validate_with(|text: &String| {
+            variable
+                .coerce(toml::Value::String(text.clone()))
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+        .interact_text()?;
+
+    variable.coerce(toml::Value::String(answer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_provided_values() {
+        let temp_dir = TempDir::new().unwrap();
+        let vars_file = temp_dir.path().join("vars.toml");
+        fs::write(&vars_file, "author = \"Ada\"\nport = 8080\n").unwrap();
+
+        let vars = vec!["author=Grace".to_string(), "db=a=b".to_string()];
+        let values = provided_values(&vars, Some(&vars_file)).unwrap();
+
+        assert_eq!(values["author"].as_str(), Some("Grace"));
+        assert_eq!(values["port"].as_integer(), Some(8080));
+        assert_eq!(values["db"].as_str(), Some("a=b"));
+
+        assert!(provided_values(&["novalue".to_string()], None).is_err());
+        assert!(provided_values(&["=x".to_string()], None).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use minisign_verify::{PublicKey, Signature, StreamVerifier};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+
+use crate::utils::config::{Config, config_path};
+use crate::utils::http::{http_client, send};
+
+/// What a template archive has to match before it is used
+///
+/// Templates can put arbitrary code into a new project, so an archive can be
+/// pinned to a sha256 checksum and/or a minisign signature made with the
+/// public key from the config file.
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    /// Expected sha256 of the archive, in hex
+    pub sha256: Option<String>,
+    /// Minisign signature of the archive, as a file path or URL
+    pub signature: Option<String>,
+}
+
+impl Verification {
+    /// Returns `true` if the archive has to be checked
+    pub fn is_required(&self) -> bool {
+        self.sha256.is_some() || self.signature.is_some()
+    }
+
+    /// Loads the signature together with the pinned public key
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if no signature was asked for, or an error if the
+    /// signature can't be read or no public key is configured
+    pub fn trusted_signature(&self) -> Result<Option<TrustedSignature>> {
+        let Some(location) = &self.signature else {
+            return Ok(None);
+        };
+
+        let Some(public_key) = Config::load()?.public_key else {
+            anyhow::bail!(
+                "Set public_key in {} to verify template signatures",
+                config_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "the wenzetu config file".to_string())
+            );
+        };
+        let key = PublicKey::from_base64(public_key.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid public_key in config: {}", e))?;
+
+        let text = read_signature(location)?;
+        let signature = Signature::decode(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid signature '{}': {}", location, e))?;
+
+        Ok(Some(TrustedSignature { key, signature }))
+    }
+}
+
+/// A signature and the public key it has to be made with
+pub struct TrustedSignature {
+    key: PublicKey,
+    signature: Signature,
+}
+
+/// Checks archive bytes against a [`Verification`] as they are read
+pub struct ArchiveVerifier<'a> {
+    expected_sha256: Option<String>,
+    sha256: Sha256,
+    signature: Option<StreamVerifier<'a>>,
+}
+
+impl<'a> ArchiveVerifier<'a> {
+    /// Creates a verifier for the checksum and signature, if any
+    ///
+    /// # Returns
+    ///
+    /// Returns the verifier, or an error if the checksum is malformed or the
+    /// signature uses an unsupported mode
+    pub fn new(
+        verification: &Verification,
+        signature: Option<&'a TrustedSignature>,
+    ) -> Result<Self> {
+        let expected_sha256 = verification
+            .sha256
+            .as_deref()
+            .map(parse_sha256)
+            .transpose()?;
+        let signature = signature
+            .map(|trusted| trusted.key.verify_stream(&trusted.signature))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Can't verify template signature: {}", e))?;
+
+        Ok(Self {
+            expected_sha256,
+            sha256: Sha256::new(),
+            signature,
+        })
+    }
+
+    /// Feeds the next chunk of the archive
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.sha256.update(bytes);
+        if let Some(signature) = &mut self.signature {
+            signature.update(bytes);
+        }
+    }
+
+    /// Wraps a reader so everything read from it is verified
+    pub fn reader<R: Read>(self, inner: R) -> VerifyingReader<'a, R> {
+        VerifyingReader {
+            inner,
+            verifier: self,
+        }
+    }
+
+    /// Checks the archive once all of it has been fed
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the archive matches, or an error if the checksum
+    /// or the signature doesn't
+    pub fn finish(mut self) -> Result<()> {
+        let actual = to_hex(&self.sha256.finalize());
+        if let Some(expected) = &self.expected_sha256
+            && *expected != actual
+        {
+            anyhow::bail!(
+                "Checksum mismatch for the template archive: expected sha256 {}, got {}. The archive may have been tampered with.",
+                expected,
+                actual
+            );
+        }
+
+        if let Some(signature) = &mut self.signature {
+            signature.finalize().map_err(|e| {
+                anyhow::anyhow!(
+                    "Signature verification failed for the template archive: {}",
+                    e
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reader feeding an [`ArchiveVerifier`]
+pub struct VerifyingReader<'a, R> {
+    inner: R,
+    verifier: ArchiveVerifier<'a>,
+}
+
+impl<R: Read> VerifyingReader<'_, R> {
+    /// Reads what is left of the stream, at most `limit` bytes, and checks
+    /// the archive
+    ///
+    /// Extraction can stop at the end of the tar data, before the end of
+    /// the download, so the rest is read to check the whole archive.
+    pub fn finish(mut self, limit: u64) -> Result<()> {
+        io::copy(&mut (&mut self).take(limit), &mut io::sink())
+            .context("Failed to read template archive")?;
+        self.verifier.finish()
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.verifier.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Checks that a checksum is 64 hex digits, and lowercases it
+fn parse_sha256(value: &str) -> Result<String> {
+    let value = value.trim().to_ascii_lowercase();
+    if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!(
+            "Invalid sha256 checksum '{}': expected 64 hex digits",
+            value
+        );
+    }
+    Ok(value)
+}
+
+/// Reads a signature from a file or an `http(s)://` URL
+fn read_signature(location: &str) -> Result<String> {
+    if location.starts_with("https://") || location.starts_with("http://") {
+        let response = send(http_client()?.get(location))
+            .with_context(|| format!("Failed to download signature '{}'", location))?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Failed to download signature '{}' (HTTP {})",
+                location,
+                response.status()
+            );
+        }
+        return Ok(response.text()?);
+    }
+
+    fs::read_to_string(location).with_context(|| format!("Failed to read signature '{}'", location))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHIVE: &[u8] = b"template archive";
+    const ARCHIVE_SHA256: &str = "da01743e46eb884410c85b7f0873d622651b16e1a0f15b69ad784f390ed16df8";
+    const PUBLIC_KEY: &str = "RWQBAgMEBQYHCOpKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs";
+    const OTHER_KEY: &str = "RWQBAgMEBQYHCP0XJDhaoMdbZPt4zWAvodmR/ev3axPFjtcC6sg16fYY";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBAgMEBQYHCH0HD1vHKXpLOJ6JWJv69XfU22clT0tC9Ixa3IGV6dUoRl5OH3113JkeWOfl2PodocFXOKtfDo+dDmEWjVMXrwg=
+trusted comment: timestamp:1760000000\tfile:template.tar.gz
+M3K0PguWGhYjDU0aloDqhwyG7oh1QCoHYuHNg8IqlGo/T7cvkfoc0cPMKdLeFQp+P9Ito3dGs15rcufTcmZCBA==
+";
+
+    fn verify(sha256: Option<&str>, key: Option<&str>, archive: &[u8]) -> Result<()> {
+        let verification = Verification {
+            sha256: sha256.map(str::to_string),
+            signature: None,
+        };
+        let trusted = key.map(|key| TrustedSignature {
+            key: PublicKey::from_base64(key).unwrap(),
+            signature: Signature::decode(SIGNATURE).unwrap(),
+        });
+
+        let mut reader = ArchiveVerifier::new(&verification, trusted.as_ref())?.reader(archive);
+        // Read part of the archive, `finish` reads the rest
+        reader.read_exact(&mut [0; 4])?;
+        reader.finish(u64::MAX)
+    }
+
+    #[test]
+    fn test_checksum() {
+        verify(Some(ARCHIVE_SHA256), None, ARCHIVE).unwrap();
+        verify(Some(&ARCHIVE_SHA256.to_uppercase()), None, ARCHIVE).unwrap();
+        verify(None, None, ARCHIVE).unwrap();
+
+        let err = verify(Some(ARCHIVE_SHA256), None, b"tampered archive").unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+
+        let err = verify(Some("abc"), None, ARCHIVE).unwrap_err();
+        assert!(err.to_string().contains("expected 64 hex digits"));
+    }
+
+    #[test]
+    fn test_signature() {
+        verify(None, Some(PUBLIC_KEY), ARCHIVE).unwrap();
+
+        let err = verify(None, Some(PUBLIC_KEY), b"tampered archive").unwrap_err();
+        assert!(err.to_string().contains("Signature verification failed"));
+
+        // A signature from another key is rejected
+        assert!(verify(None, Some(OTHER_KEY), ARCHIVE).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+
+use super::cache::TemplateCache;
+use super::clone::{git_cache_key, list_git_tags};
+use super::download::{DownloadOptions, list_tags};
+use super::host::RemoteRepo;
+use super::source::TemplateSource;
+
+/// A template name with an optional version requirement, e.g. `default@^1.2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateSpec {
+    /// Template name
+    pub name: String,
+    /// Versions the template may be taken from
+    pub requirement: Option<VersionReq>,
+}
+
+impl TemplateSpec {
+    /// Splits `name@requirement`
+    ///
+    /// # Returns
+    ///
+    /// Returns the spec, or an error if the requirement isn't valid semver
+    pub fn parse(value: &str) -> Result<Self> {
+        let Some((name, requirement)) = value.split_once('@') else {
+            return Ok(Self {
+                name: value.to_string(),
+                requirement: None,
+            });
+        };
+
+        if name.is_empty() {
+            anyhow::bail!("Missing template name in '{}'", value);
+        }
+        let requirement = VersionReq::parse(requirement).with_context(|| {
+            format!(
+                "Invalid version requirement '{}' for template '{}'",
+                requirement, name
+            )
+        })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            requirement: Some(requirement),
+        })
+    }
+}
+
+/// Picks the newest tag of a repository matching a version requirement
+///
+/// Tags named after the template (`default@1.2.0`, `default-v1.2.0`) are
+/// used when the repository has any, so templates in one repository can be
+/// versioned separately. Otherwise repository-wide tags (`v1.2.0`, `1.2.0`)
+/// are used. `--offline` runs only consider tags already in the cache.
+///
+/// # Arguments
+///
+/// * `source` - Repository holding the template
+/// * `template` - Template name
+/// * `requirement` - Versions to accept
+/// * `options` - Host and network options
+///
+/// # Returns
+///
+/// Returns the tag to use, or an error if no tag matches
+pub fn resolve_version(
+    source: &TemplateSource,
+    template: &str,
+    requirement: &VersionReq,
+    options: &DownloadOptions,
+) -> Result<String> {
+    let tags = match source {
+        TemplateSource::Remote(url) if options.offline => {
+            let repo =
+                RemoteRepo::parse(url, options.provider.as_deref(), options.api_url.as_deref())?;
+            cached_refs(&repo.cache_key())?
+        }
+        TemplateSource::Remote(url) => list_tags(url, options)?,
+        TemplateSource::Git(url) if options.offline => cached_refs(&git_cache_key(url))?,
+        TemplateSource::Git(url) => list_git_tags(url)?,
+        _ => anyhow::bail!(
+            "Version requirements need a git repository or a registry index, not a local template"
+        ),
+    };
+
+    newest_matching(&tags, template, requirement)
+}
+
+/// References of a repository that are in the template cache
+fn cached_refs(repo_key: &str) -> Result<Vec<String>> {
+    TemplateCache::open()?.refs(repo_key)
+}
+
+/// Picks the tag with the highest version matching the requirement
+fn newest_matching(tags: &[String], template: &str, requirement: &VersionReq) -> Result<String> {
+    let template_tags: Vec<(Version, &String)> = tags
+        .iter()
+        .filter_map(|tag| template_tag_version(tag, template).map(|version| (version, tag)))
+        .collect();
+    let versions = if template_tags.is_empty() {
+        tags.iter()
+            .filter_map(|tag| parse_version(tag).map(|version| (version, tag)))
+            .collect()
+    } else {
+        template_tags
+    };
+
+    if let Some((_, tag)) = versions
+        .iter()
+        .filter(|(version, _)| requirement.matches(version))
+        .max_by(|a, b| a.0.cmp(&b.0))
+    {
+        return Ok(tag.to_string());
+    }
+
+    let mut available: Vec<&Version> = versions.iter().map(|(version, _)| version).collect();
+    available.sort();
+    if available.is_empty() {
+        anyhow::bail!(
+            "No version tags found for template '{}'. Tag releases as v1.2.0 or {}@1.2.0.",
+            template,
+            template
+        );
+    }
+    anyhow::bail!(
+        "No version of template '{}' matches {}. Available versions: {}",
+        template,
+        requirement,
+        available
+            .iter()
+            .map(|version| version.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Version in a tag named after a template, e.g. `default@1.2.0`
+fn template_tag_version(tag: &str, template: &str) -> Option<Version> {
+    let rest = tag.strip_prefix(template)?;
+    let version = rest
+        .strip_prefix('@')
+        .or_else(|| rest.strip_prefix("-v"))
+        .or_else(|| rest.strip_prefix("/v"))?;
+    parse_version(version)
+}
+
+/// Version in a tag like `v1.2.0` or `1.2.0`
+pub fn parse_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let spec = TemplateSpec::parse("default@^1.2").unwrap();
+        assert_eq!(spec.name, "default");
+        assert_eq!(spec.requirement, Some(VersionReq::parse("^1.2").unwrap()));
+
+        assert_eq!(TemplateSpec::parse("default").unwrap().requirement, None);
+        assert!(TemplateSpec::parse("default@latest").is_err());
+        assert!(TemplateSpec::parse("@1.0").is_err());
+    }
+
+    #[test]
+    fn test_newest_matching() {
+        let repo_tags = tags(&[
+            "v1.1.0",
+            "v1.2.0",
+            "v1.4.2",
+            "v2.0.0",
+            "v1.5.0-beta.1",
+            "main",
+        ]);
+        let req = VersionReq::parse("^1.2").unwrap();
+        assert_eq!(
+            newest_matching(&repo_tags, "default", &req).unwrap(),
+            "v1.4.2"
+        );
+
+        let err = newest_matching(&repo_tags, "default", &VersionReq::parse("^3").unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Available versions: 1.1.0, 1.2.0, 1.4.2, 1.5.0-beta.1, 2.0.0"));
+
+        // Tags named after the template win over repository-wide tags
+        let template_tags = tags(&["v1.9.0", "default@1.3.0", "default-v1.2.5", "api@1.8.0"]);
+        assert_eq!(
+            newest_matching(&template_tags, "default", &req).unwrap(),
+            "default@1.3.0"
+        );
+
+        assert!(newest_matching(&tags(&["main"]), "default", &req).is_err());
+    }
+}
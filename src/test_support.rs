@@ -0,0 +1,197 @@
+//! Helpers shared by unit tests
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request received by [`StubServer`]
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    /// Path including the query string
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+}
+
+/// A canned response served by [`StubServer`]
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StubResponse {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+type Handler = dyn Fn(&StubRequest) -> StubResponse + Send + Sync;
+
+/// A minimal HTTP/1.1 server on localhost for exercising network code
+pub struct StubServer {
+    port: u16,
+    running: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<StubRequest>>>,
+}
+
+impl StubServer {
+    /// Starts a server that answers every request with `handler`
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&StubRequest) -> StubResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let running = Arc::new(AtomicBool::new(true));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let flag = running.clone();
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let handler = handler.clone();
+                    let log = log.clone();
+                    thread::spawn(move || serve(stream, &*handler, &log));
+                }
+            }
+        });
+
+        Self {
+            port,
+            running,
+            requests,
+        }
+    }
+
+    /// Starts a server that serves fixed responses by path
+    ///
+    /// Unknown paths get a 404.
+    pub fn with_routes(routes: Vec<(&str, StubResponse)>) -> Self {
+        let routes: HashMap<String, StubResponse> = routes
+            .into_iter()
+            .map(|(path, response)| (path.to_string(), response))
+            .collect();
+        Self::start(move |request| {
+            routes
+                .get(&request.path)
+                .cloned()
+                .unwrap_or_else(|| StubResponse::status(404))
+        })
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:1234`
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wake the accept loop so it notices the flag
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+    }
+}
+
+fn serve(stream: TcpStream, handler: &Handler, log: &Mutex<Vec<StubRequest>>) {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() || request_line.is_empty() {
+        return;
+    }
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    // Drain any request body
+    if let Some(length) = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        let mut body = vec![0; length];
+        let _ = reader.read_exact(&mut body);
+    }
+
+    let request = StubRequest { path, headers };
+    log.lock().unwrap().push(request.clone());
+    let response = handler(&request);
+
+    let mut stream = reader.into_inner();
+    let mut head = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+    let _ = stream.flush();
+}
+
+/// Builds a gzipped tarball from `(path, contents)` pairs
+pub fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
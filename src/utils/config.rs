@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that overrides the config file location
+const CONFIG_ENV: &str = "WENZETU_CONFIG";
+
+/// User configuration, read from `~/.config/wenzetu/config.toml`
+///
+/// ```toml
+/// ca_bundle = "/etc/ssl/certs/corporate-ca.pem"
+/// public_key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"
+/// registries = ["https://templates.example.com/index.toml"]
+///
+/// [credentials."github.com"]
+/// token = "ghp_..."
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// PEM file with extra root certificates to trust, e.g. for an
+    /// intercepting proxy
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Minisign public key that template signatures must be made with
+    #[serde(default)]
+    pub public_key: Option<String>,
+
+    /// Registry index files or URLs that template names are looked up in
+    #[serde(default)]
+    pub registries: Vec<String>,
+
+    /// Access tokens keyed by host (including the port, if any)
+    #[serde(default)]
+    pub credentials: HashMap<String, Credential>,
+}
+
+/// A credentials entry in the config file
+#[derive(Debug, Clone, Deserialize)]
+pub struct Credential {
+    pub token: String,
+}
+
+impl Config {
+    /// Loads the config file from its default location
+    ///
+    /// Returns an empty config if the file doesn't exist.
+    pub fn load() -> Result<Self> {
+        match config_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Loads a config file, returning an empty config if it doesn't exist
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file '{}'", path.display()))
+    }
+
+    /// Returns the token configured for a host
+    pub fn token_for(&self, host: &str) -> Option<&str> {
+        self.credentials.get(host).map(|c| c.token.as_str())
+    }
+}
+
+/// Location of the config file
+///
+/// Uses `$WENZETU_CONFIG` when set, otherwise the platform config directory
+/// (e.g. `~/.config/wenzetu/config.toml` on Linux).
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    dirs::config_dir().map(|dir| dir.join("wenzetu").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_credentials() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "[credentials.\"gitlab.example.com\"]\ntoken = \"secret\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.token_for("gitlab.example.com"), Some("secret"));
+        assert_eq!(config.token_for("github.com"), None);
+        assert_eq!(config.ca_bundle, None);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config::load_from(&temp_dir.path().join("missing.toml")).unwrap();
+        assert!(config.credentials.is_empty());
+    }
+}
@@ -1,44 +1,165 @@
 use anyhow::{Context, Result};
-use std::fs;
-use std::path::Path;
-use walkdir::WalkDir;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
 
-/// Copies a directory and all its contents recursively
+/// Number of bytes looked at to tell binary files from text
+const SNIFF_LEN: usize = 8192;
+
+/// Signatures at the start of common binary formats
+const MAGIC_NUMBERS: &[&[u8]] = &[
+    b"\x89PNG\r\n\x1a\n",   // PNG
+    b"\xff\xd8\xff",        // JPEG
+    b"GIF87a",              // GIF
+    b"GIF89a",              // GIF
+    b"\x00\x00\x01\x00",    // ICO
+    b"%PDF-",               // PDF
+    b"PK\x03\x04",          // ZIP, JAR, Office documents
+    b"\x1f\x8b",            // gzip
+    b"\xfd7zXZ\x00",        // xz
+    b"\x28\xb5\x2f\xfd",    // zstd
+    b"7z\xbc\xaf\x27\x1c",  // 7-Zip
+    b"wOFF",                // WOFF
+    b"wOF2",                // WOFF2
+    b"OTTO",                // OpenType
+    b"\x00\x01\x00\x00",    // TrueType
+    b"SQLite format 3\x00", // SQLite
+    b"\x7fELF",             // ELF
+    b"\xcf\xfa\xed\xfe",    // Mach-O
+    b"\xca\xfe\xba\xbe",    // Mach-O universal, Java class
+    b"\x00asm",             // WebAssembly
+];
+
+/// Recreates a symlink inside a destination directory
+///
+/// Only relative links that stay inside the destination are recreated (see
+/// [`symlink_stays_inside`]). A file or link already at the path is
+/// replaced.
 ///
 /// # Arguments
 ///
-/// * `src` - Source directory path
-/// * `dst` - Destination directory path
-///
-/// # Examples
-///
-/// ```no_run
-/// use std::path::Path;
-/// copy_dir_recursively(Path::new("./source"), Path::new("./dest")).unwrap();
-/// ```
-pub fn copy_dir_recursively(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
-
-    for entry in WalkDir::new(src).min_depth(1) {
-        let entry = entry?;
-        let path = entry.path();
-        let relative_path = path.strip_prefix(src)?;
-        let target_path = dst.join(relative_path);
-
-        if entry.file_type().is_dir() {
-            fs::create_dir_all(&target_path)?;
-        } else {
-            if let Some(parent) = target_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::copy(path, &target_path)?;
+/// * `source` - Symlink to recreate
+/// * `dest` - Destination directory
+/// * `relative_path` - Path of the new link inside `dest`
+///
+/// # Returns
+///
+/// Returns `Ok(())` if successful, or an error if the link points outside
+/// the destination
+pub fn copy_symlink(source: &Path, dest: &Path, relative_path: &Path) -> Result<()> {
+    let link = fs::read_link(source)
+        .with_context(|| format!("Failed to read symlink '{}'", source.display()))?;
+    if !symlink_stays_inside(relative_path, &link) {
+        anyhow::bail!(
+            "Symlink '{}' -> '{}' points outside the project",
+            relative_path.display(),
+            link.display()
+        );
+    }
+
+    let target_path = dest.join(relative_path);
+    if target_path
+        .symlink_metadata()
+        .is_ok_and(|metadata| !metadata.is_dir())
+    {
+        fs::remove_file(&target_path)?;
+    }
+    create_symlink(&link, &target_path)
+        .with_context(|| format!("Failed to create symlink '{}'", target_path.display()))
+}
+
+/// Checks that a symlink target resolves inside the directory the link is
+/// created in
+///
+/// Targets have to be relative, and may only use `..` at their start, to
+/// climb no higher than the root of the directory. Allowing `..` further on
+/// would let a target pass through another link (`dir -> .`, then
+/// `dir/../x`) and escape.
+///
+/// # Arguments
+///
+/// * `relative_path` - Path of the link inside the directory
+/// * `link` - Target of the link
+pub fn symlink_stays_inside(relative_path: &Path, link: &Path) -> bool {
+    let depth = relative_path
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+    let mut climbs = 0;
+    let mut descended = false;
+
+    for component in link.components() {
+        match component {
+            Component::ParentDir if !descended => climbs += 1,
+            Component::Normal(_) => descended = true,
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
         }
     }
+    climbs <= depth
+}
 
-    Ok(())
+/// Creates the parent directories of a path inside a directory, resolving
+/// the symlinks on the way
+///
+/// Links recreated earlier, by an earlier layer or earlier in the same
+/// template, can make a path lead somewhere else than its text says. The
+/// parent directory is checked to resolve inside `root` before anything is
+/// created in it.
+///
+/// # Arguments
+///
+/// * `root` - Canonical path of the directory
+/// * `relative_path` - Path inside `root`
+///
+/// # Returns
+///
+/// Returns the path relative to `root` with its parent directories
+/// resolved, or an error if they lead outside `root`
+pub fn resolve_inside(root: &Path, relative_path: &Path) -> Result<PathBuf> {
+    let parent = root.join(relative_path.parent().unwrap_or(Path::new("")));
+    let escapes = || {
+        anyhow::anyhow!(
+            "'{}' leads outside the project through a symlink",
+            relative_path.display()
+        )
+    };
+
+    // Directories yet to be created end up under the deepest existing one
+    let mut existing = parent.as_path();
+    while existing.symlink_metadata().is_err() {
+        existing = existing.parent().ok_or_else(escapes)?;
+    }
+    if !fs::canonicalize(existing)?.starts_with(root) {
+        return Err(escapes());
+    }
+
+    fs::create_dir_all(&parent)?;
+    let resolved = fs::canonicalize(&parent)?;
+    let resolved = resolved.strip_prefix(root).map_err(|_| escapes())?;
+    Ok(match relative_path.file_name() {
+        Some(name) => resolved.join(name),
+        None => resolved.to_path_buf(),
+    })
+}
+
+#[cfg(unix)]
+fn create_symlink(link: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(link, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(link: &Path, path: &Path) -> io::Result<()> {
+    let resolved = path.parent().unwrap_or(Path::new("")).join(link);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(link, path)
+    } else {
+        std::os::windows::fs::symlink_file(link, path)
+    }
 }
 
-/// Checks if a path is a binary file based on its extension
+/// Checks if a file is binary by looking at its first bytes
+///
+/// Files that can't be read are judged by their extension.
 ///
 /// # Arguments
 ///
@@ -48,6 +169,53 @@ pub fn copy_dir_recursively(src: &Path, dst: &Path) -> Result<()> {
 ///
 /// `true` if the file is likely binary, `false` otherwise
 pub fn is_binary_file(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    match File::open(path).and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head)) {
+        Ok(_) => is_binary_content(&head),
+        Err(_) => has_binary_extension(path),
+    }
+}
+
+/// Checks if the start of a file looks binary
+///
+/// Known magic numbers (images, fonts, archives, SQLite databases,
+/// executables), NUL bytes, bytes that aren't UTF-8 and a high share of
+/// control characters all mark a file as binary.
+///
+/// # Arguments
+///
+/// * `head` - First bytes of the file
+pub fn is_binary_content(head: &[u8]) -> bool {
+    if MAGIC_NUMBERS.iter().any(|magic| head.starts_with(magic)) || is_riff_media(head) {
+        return true;
+    }
+    if head.contains(&0) {
+        return true;
+    }
+    // A character cut off at the end of the sample is fine
+    if let Err(e) = std::str::from_utf8(head)
+        && e.error_len().is_some()
+    {
+        return true;
+    }
+
+    let control = head
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > head.len()
+}
+
+/// WebP images, WAV audio and AVI video
+fn is_riff_media(head: &[u8]) -> bool {
+    head.starts_with(b"RIFF")
+        && head
+            .get(8..12)
+            .is_some_and(|kind| matches!(kind, b"WEBP" | b"WAVE" | b"AVI "))
+}
+
+/// Checks for the extensions of compiled binaries
+fn has_binary_extension(path: &Path) -> bool {
     path.extension()
         .and_then(|s| s.to_str())
         .map(|s| matches!(s, "exe" | "dll" | "so" | "dylib" | "bin" | "o" | "a"))
@@ -71,8 +239,8 @@ pub fn should_skip_path(path: &Path) -> bool {
         return true;
     }
 
-    // Skip binary files
-    if is_binary_file(path) {
+    // Skip compiled binaries
+    if has_binary_extension(path) {
         return true;
     }
 
@@ -104,6 +272,57 @@ mod tests {
         assert!(!is_binary_file(Path::new("Cargo.toml")));
     }
 
+    #[test]
+    fn test_is_binary_content() {
+        assert!(is_binary_content(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"));
+        assert!(is_binary_content(b"wOF2\x00\x01\x00\x00"));
+        assert!(is_binary_content(b"SQLite format 3\x00"));
+        assert!(is_binary_content(b"\x00\x00\x01\x00\x01\x00\x10\x10"));
+        assert!(is_binary_content(b"RIFF\x24\x00\x00\x00WEBPVP8 "));
+        assert!(is_binary_content(b"text with a \x00 byte"));
+        assert!(is_binary_content(b"\xff\xfe\xfd not utf-8"));
+        assert!(is_binary_content(b"\x01\x02\x03\x04 mostly control"));
+
+        assert!(!is_binary_content(b"fn main() {}\n"));
+        assert!(!is_binary_content(
+            "h\u{e9}llo w\u{f6}rld \u{1f980}".as_bytes()
+        ));
+        assert!(!is_binary_content(b"!function(){return\"{{x}}\"}();"));
+        assert!(!is_binary_content(b""));
+        // A multi-byte character cut off by the sample size
+        assert!(!is_binary_content(&"caf\u{e9}".as_bytes()[..4]));
+    }
+
+    #[test]
+    fn test_is_binary_file_reads_content() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let icon = temp_dir.path().join("favicon.txt");
+        fs::write(&icon, b"\x00\x00\x01\x00\x01\x00").unwrap();
+        let script = temp_dir.path().join("app.bin");
+        fs::write(&script, "console.log('{{ name }}')").unwrap();
+
+        assert!(is_binary_file(&icon));
+        assert!(!is_binary_file(&script));
+    }
+
+    #[test]
+    fn test_symlink_stays_inside() {
+        let inside = |link_path: &str, link: &str| {
+            symlink_stays_inside(Path::new(link_path), Path::new(link))
+        };
+
+        assert!(inside("config.toml", "shared/config.toml"));
+        assert!(inside("scripts/dev.sh", "../bin/dev.sh"));
+        assert!(inside("a/b/root", "../.."));
+        assert!(inside("current", "./v2"));
+
+        assert!(!inside("config.toml", "../config.toml"));
+        assert!(!inside("a/b/up", "../../.."));
+        assert!(!inside("config.toml", "/etc/passwd"));
+        // `..` after a name could climb through another link
+        assert!(!inside("escape", "dir/../x"));
+    }
+
     #[test]
     fn test_should_skip_path() {
         assert!(should_skip_path(Path::new("src/target/debug/app")));
@@ -0,0 +1,410 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::NamedTempFile;
+
+use super::config::Config;
+
+/// User agent sent with every request
+const USER_AGENT: &str = "unc-cli";
+
+/// How long to wait for a connection to be established
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for a response, and for each read of its body
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable pointing at a PEM file of extra root certificates
+const CA_BUNDLE_ENV: &str = "WENZETU_CA_BUNDLE";
+
+/// How many times a transient failure is retried
+const RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled for every retry after it
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Builds the HTTP client used for every request the CLI makes
+///
+/// Proxies come from `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` (or their
+/// lowercase forms), with hosts in `NO_PROXY` reached directly. Certificates
+/// from the [`ca_bundle`] are trusted on top of the system roots.
+pub fn http_client() -> Result<Client> {
+    let proxies = proxies_from(|name| std::env::var(name).ok())?;
+    build_client(READ_TIMEOUT, ca_bundle()?.as_deref(), proxies)
+}
+
+fn build_client(
+    read_timeout: Duration,
+    ca_bundle: Option<&Path>,
+    proxies: Vec<Proxy>,
+) -> Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(read_timeout)
+        .no_proxy();
+
+    for proxy in proxies {
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = ca_bundle {
+        for certificate in load_certificates(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder.build().context("Failed to set up the HTTP client")
+}
+
+/// Reads the proxies to use from the environment
+///
+/// Each variable is looked up in upper case, then lower case. Hosts listed
+/// in `NO_PROXY` bypass every proxy.
+fn proxies_from<E>(env: E) -> Result<Vec<Proxy>>
+where
+    E: Fn(&str) -> Option<String>,
+{
+    let var = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_lowercase()))
+            .filter(|value| !value.trim().is_empty())
+    };
+    let no_proxy = var("NO_PROXY").and_then(|hosts| NoProxy::from_string(&hosts));
+    let invalid = |name: &str| format!("Invalid proxy URL in {}", name);
+
+    let mut proxies = Vec::new();
+    if let Some(url) = var("HTTPS_PROXY") {
+        proxies.push(Proxy::https(url.as_str()).with_context(|| invalid("HTTPS_PROXY"))?);
+    }
+    if let Some(url) = var("HTTP_PROXY") {
+        proxies.push(Proxy::http(url.as_str()).with_context(|| invalid("HTTP_PROXY"))?);
+    }
+    if let Some(url) = var("ALL_PROXY") {
+        proxies.push(Proxy::all(url.as_str()).with_context(|| invalid("ALL_PROXY"))?);
+    }
+
+    Ok(proxies
+        .into_iter()
+        .map(|proxy| proxy.no_proxy(no_proxy.clone()))
+        .collect())
+}
+
+/// Location of the extra root certificates to trust, if any
+///
+/// Uses `$WENZETU_CA_BUNDLE` when set, otherwise `ca_bundle` from the
+/// config file.
+pub fn ca_bundle() -> Result<Option<PathBuf>> {
+    if let Some(path) = std::env::var_os(CA_BUNDLE_ENV).filter(|p| !p.is_empty()) {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    Ok(Config::load()?.ca_bundle)
+}
+
+/// Writes the system root certificates followed by a CA bundle to a
+/// temporary file
+///
+/// git only trusts the file `GIT_SSL_CAINFO` points at, so the bundle is
+/// passed on together with the roots git would otherwise use. When those
+/// can't be found, the file holds the bundle alone.
+///
+/// # Arguments
+///
+/// * `ca_bundle` - PEM bundle of extra root certificates
+pub fn combined_ca_bundle(ca_bundle: &Path) -> Result<NamedTempFile> {
+    let mut pem = openssl_probe::probe()
+        .cert_file
+        .and_then(|path| fs::read(path).ok())
+        .unwrap_or_default();
+    if !pem.is_empty() && !pem.ends_with(b"\n") {
+        pem.push(b'\n');
+    }
+    pem.extend(
+        fs::read(ca_bundle)
+            .with_context(|| format!("Failed to read CA bundle '{}'", ca_bundle.display()))?,
+    );
+
+    let mut file = NamedTempFile::new().context("Failed to write CA bundle for git")?;
+    io::Write::write_all(&mut file, &pem).context("Failed to write CA bundle for git")?;
+    Ok(file)
+}
+
+/// Reads the certificates from a PEM bundle
+fn load_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    let pem =
+        fs::read(path).with_context(|| format!("Failed to read CA bundle '{}'", path.display()))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Invalid certificate in CA bundle '{}'", path.display()))?;
+
+    if certificates.is_empty() {
+        anyhow::bail!("No certificates found in CA bundle '{}'", path.display());
+    }
+
+    Ok(certificates)
+}
+
+/// Sends a request, retrying transient failures with exponential backoff
+///
+/// Connection errors, timeouts and 5xx responses are retried. Rate-limit
+/// responses are returned as they are, since retrying won't help until
+/// the limit resets.
+///
+/// # Arguments
+///
+/// * `request` - Request to send (must not have a streaming body)
+///
+/// # Returns
+///
+/// Returns the last response, or the last error if every attempt failed
+pub fn send(request: RequestBuilder) -> Result<Response> {
+    send_with_retries(request, RETRIES, RETRY_DELAY)
+}
+
+fn send_with_retries(request: RequestBuilder, retries: u32, delay: Duration) -> Result<Response> {
+    let mut attempt = 0;
+    loop {
+        let this = request.try_clone().context("Request can't be retried")?;
+        match this.send() {
+            Ok(response) if attempt < retries && is_transient(&response) => {}
+            Ok(response) => return Ok(response),
+            Err(e) if attempt < retries && (e.is_timeout() || e.is_connect()) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        thread::sleep(delay * 2u32.pow(attempt));
+        attempt += 1;
+    }
+}
+
+/// Returns `true` for server errors worth retrying
+fn is_transient(response: &Response) -> bool {
+    let status = response.status();
+    status.is_server_error()
+        || (status == StatusCode::TOO_MANY_REQUESTS && rate_limit(response).is_none())
+}
+
+/// A rate-limit response from a git host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// When the limit resets, in seconds since the Unix epoch
+    pub reset: Option<u64>,
+}
+
+/// Detects rate-limit responses
+///
+/// GitHub answers 403 or 429 with `x-ratelimit-remaining: 0`, GitLab and
+/// Gitea use `ratelimit-remaining`, and secondary limits send
+/// `retry-after`.
+///
+/// # Returns
+///
+/// Returns the rate limit, or `None` if the response isn't one
+pub fn rate_limit(response: &Response) -> Option<RateLimit> {
+    let status = response.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let exhausted = ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .iter()
+        .any(|name| header(name) == Some("0"));
+    let retry_after = header("retry-after").and_then(|value| value.parse::<u64>().ok());
+
+    if !exhausted && retry_after.is_none() {
+        return None;
+    }
+
+    let reset = ["x-ratelimit-reset", "ratelimit-reset"]
+        .iter()
+        .find_map(|name| header(name).and_then(|value| value.parse().ok()))
+        .or_else(|| retry_after.map(|seconds| unix_now() + seconds));
+
+    Some(RateLimit { reset })
+}
+
+impl RateLimit {
+    /// Describes when the limit resets, e.g. `at 14:05 UTC (in 12 minutes)`
+    pub fn describe_reset(&self) -> String {
+        let Some(reset) = self.reset else {
+            return "later".to_string();
+        };
+
+        let minutes = reset.saturating_sub(unix_now()).div_ceil(60);
+        let time_of_day = reset % 86_400;
+        format!(
+            "at {:02}:{:02} UTC (in {} minute{})",
+            time_of_day / 3600,
+            time_of_day % 3600 / 60,
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        )
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubResponse, StubServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    /// Self-signed certificate used as a stand-in corporate CA
+    const TEST_CA: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBizCCATGgAwIBAgIUDkN331IsbkBvEjZ0skFAalJzuXswCgYIKoZIzj0EAwIw\n\
+GjEYMBYGA1UEAwwPd2VuemV0dSB0ZXN0IENBMCAXDTI2MTAxNjIzMTgxMFoYDzIx\n\
+MjYwOTIyMjMxODEwWjAaMRgwFgYDVQQDDA93ZW56ZXR1IHRlc3QgQ0EwWTATBgcq\n\
+hkjOPQIBBggqhkjOPQMBBwNCAAS4wXG3ClJMsQ1eldWTxW4k2FzLNa+vPVcroSY2\n\
+tJF267E9xjmb8UkMgDCIf0lO2DYrmERB9V45vfm/zMNzwB8Do1MwUTAdBgNVHQ4E\n\
+FgQUfApGkKHCXy8av8mHyM5IOuqC9eMwHwYDVR0jBBgwFoAUfApGkKHCXy8av8mH\n\
+yM5IOuqC9eMwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEA9tdz\n\
+qhw5cDEz+O4kDm6mXcgKrUfxDW8CvLvwcobwVPACIDlM2EjM6JJh3rGDy5t6vpfH\n\
+uxew9CMXNQT1AuPG5CgW\n\
+-----END CERTIFICATE-----\n";
+
+    #[test]
+    fn test_retries_transient_errors() {
+        let calls = AtomicUsize::new(0);
+        let server = StubServer::start(move |_| {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                StubResponse::status(503)
+            } else {
+                StubResponse::ok("done")
+            }
+        });
+
+        let request = http_client().unwrap().get(server.url());
+        let response = send_with_retries(request, 3, Duration::from_millis(1)).unwrap();
+        assert_eq!(response.text().unwrap(), "done");
+        assert_eq!(server.requests().len(), 3);
+
+        // Gives up after the last retry and returns the failure
+        let server = StubServer::start(|_| StubResponse::status(502));
+        let request = http_client().unwrap().get(server.url());
+        let response = send_with_retries(request, 2, Duration::from_millis(1)).unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_retries_timeouts() {
+        let server = StubServer::start(|_| {
+            thread::sleep(Duration::from_millis(500));
+            StubResponse::ok("slow")
+        });
+
+        let client = build_client(Duration::from_millis(100), None, Vec::new()).unwrap();
+        let err =
+            send_with_retries(client.get(server.url()), 1, Duration::from_millis(1)).unwrap_err();
+        assert!(err.downcast_ref::<reqwest::Error>().unwrap().is_timeout());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let server = StubServer::with_routes(vec![
+            (
+                "/github",
+                StubResponse::status(403)
+                    .header("x-ratelimit-remaining", "0")
+                    .header("x-ratelimit-reset", "1700000000"),
+            ),
+            (
+                "/secondary",
+                StubResponse::status(429).header("retry-after", "120"),
+            ),
+            ("/forbidden", StubResponse::status(403)),
+        ]);
+        let get = |path: &str| {
+            let request = http_client()
+                .unwrap()
+                .get(format!("{}{}", server.url(), path));
+            send_with_retries(request, 3, Duration::from_millis(1)).unwrap()
+        };
+
+        let limit = rate_limit(&get("/github")).unwrap();
+        assert_eq!(limit.reset, Some(1_700_000_000));
+        assert!(limit.describe_reset().starts_with("at 22:13 UTC"));
+
+        let limit = rate_limit(&get("/secondary")).unwrap();
+        assert!(limit.describe_reset().ends_with("(in 2 minutes)"));
+
+        assert_eq!(rate_limit(&get("/forbidden")), None);
+        // Rate limits are not retried
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_ca_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundle = temp_dir.path().join("ca.pem");
+        fs::write(&bundle, TEST_CA).unwrap();
+
+        assert_eq!(load_certificates(&bundle).unwrap().len(), 1);
+        build_client(READ_TIMEOUT, Some(&bundle), Vec::new()).unwrap();
+
+        // git gets the bundle on top of the system roots
+        let combined = fs::read_to_string(combined_ca_bundle(&bundle).unwrap().path()).unwrap();
+        assert!(combined.ends_with(TEST_CA));
+
+        let missing = temp_dir.path().join("missing.pem");
+        let err = build_client(READ_TIMEOUT, Some(&missing), Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("Failed to read CA bundle"));
+
+        let empty = temp_dir.path().join("empty.pem");
+        fs::write(&empty, "not a certificate\n").unwrap();
+        let err = load_certificates(&empty).unwrap_err();
+        assert!(err.to_string().contains("No certificates found"));
+    }
+
+    #[test]
+    fn test_proxies_from_env() {
+        let proxy = StubServer::start(|_| StubResponse::ok("proxied"));
+        let direct = StubServer::start(|_| StubResponse::ok("direct"));
+        let env = |no_proxy: &'static str| {
+            let proxy_url = proxy.url();
+            move |name: &str| match name {
+                "http_proxy" => Some(proxy_url.clone()),
+                "NO_PROXY" => Some(no_proxy.to_string()),
+                _ => None,
+            }
+        };
+        let get = |no_proxy, url: &str| {
+            let client = build_client(READ_TIMEOUT, None, proxies_from(env(no_proxy)).unwrap());
+            send(client.unwrap().get(url)).unwrap().text().unwrap()
+        };
+
+        // Requests go through the proxy, which sees the full URL
+        assert_eq!(get("", "http://templates.example/repo.tar.gz"), "proxied");
+        assert_eq!(
+            proxy.requests()[0].path,
+            "http://templates.example/repo.tar.gz"
+        );
+
+        // Hosts in NO_PROXY are reached directly
+        assert_eq!(get("127.0.0.1", &direct.url()), "direct");
+        assert_eq!(proxy.requests().len(), 1);
+        assert_eq!(direct.requests().len(), 1);
+
+        assert!(proxies_from(|name| (name == "HTTPS_PROXY").then(|| "::".to_string())).is_err());
+    }
+}
@@ -1,12 +1,15 @@
 pub mod cargo;
+pub mod config;
 pub mod file_ops;
 pub mod git;
+pub mod http;
 pub mod tailwind;
 pub mod validation;
 
 // Re-export commonly used functions
 pub use cargo::{ensure_cargo_watch, get_run_command, is_cargo_watch_installed};
-pub use file_ops::{copy_dir_recursively, ensure_directory, should_skip_path};
-pub use git::init_git_repo;
+pub use file_ops::{ensure_directory, is_binary_file, should_skip_path};
+pub use git::{init_git_repo, is_git_available};
+pub use http::http_client;
 pub use tailwind::{build_tailwind_args, is_tailwind_enabled, read_tailwind_config};
-pub use validation::validate_project_name;
+pub use validation::{validate_project_name, validate_reference};
@@ -17,6 +17,26 @@ pub fn validate_project_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Validates a branch, tag or commit SHA before it is handed to git or a host
+///
+/// Rules:
+/// - Must not be empty or start with `-`, so git can't read it as an option
+/// - Must not contain whitespace or control characters
+pub fn validate_reference(reference: &str) -> Result<()> {
+    if reference.is_empty()
+        || reference.starts_with('-')
+        || reference
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+    {
+        anyhow::bail!(
+            "Invalid ref '{}'. Refs can't be empty, start with '-' or contain whitespace.",
+            reference.escape_debug()
+        );
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,4 +59,16 @@ mod tests {
         assert!(validate_project_name("my@project").is_err());
         assert!(validate_project_name("").is_err());
     }
+
+    #[test]
+    fn test_references() {
+        assert!(validate_reference("main").is_ok());
+        assert!(validate_reference("feature/new-layout").is_ok());
+        assert!(validate_reference("default@1.4.0").is_ok());
+        assert!(validate_reference("--upload-pack=touch /tmp/x").is_err());
+        assert!(validate_reference("-b").is_err());
+        assert!(validate_reference("main branch").is_err());
+        assert!(validate_reference("main\n").is_err());
+        assert!(validate_reference("").is_err());
+    }
 }